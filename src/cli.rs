@@ -0,0 +1,101 @@
+use anyhow::{Result, anyhow, bail};
+use std::path::PathBuf;
+
+// [migrate [DIR]] --config <FILE> --screenshots <DIR> --recipes <DIR> --font <FILE>, overriding the
+// config file
+#[derive(Default)]
+pub struct Args {
+    pub command: Option<Command>,
+    pub config: Option<PathBuf>,
+    pub screenshots: Option<PathBuf>,
+    pub recipes: Option<PathBuf>,
+    pub font: Option<PathBuf>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    // upgrades the recipes in `DIR`, or in the recipe directory, instead of starting the app
+    Migrate(Option<PathBuf>),
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                match &mut parsed.command {
+                    None if arg == "migrate" => parsed.command = Some(Command::Migrate(None)),
+                    Some(Command::Migrate(dir @ None)) => *dir = Some(PathBuf::from(arg)),
+                    _ => bail!("unknown argument: {arg}"),
+                }
+
+                continue;
+            }
+
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+
+            let field = match flag.as_str() {
+                "--config" => &mut parsed.config,
+                "--screenshots" => &mut parsed.screenshots,
+                "--recipes" => &mut parsed.recipes,
+                "--font" => &mut parsed.font,
+                _ => bail!("unknown argument: {flag}"),
+            };
+
+            let value = value
+                .or_else(|| args.next())
+                .ok_or_else(|| anyhow!("missing value for {flag}"))?;
+
+            *field = Some(PathBuf::from(value));
+        }
+
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args> {
+        Args::parse(args.iter().map(ToString::to_string))
+    }
+
+    #[test]
+    fn accepts_both_forms() -> Result<()> {
+        let args = parse(&["--recipes", "a b", "--font=c.ttf"])?;
+
+        assert_eq!(args.recipes, Some(PathBuf::from("a b")));
+        assert_eq!(args.font, Some(PathBuf::from("c.ttf")));
+        assert_eq!(args.config, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unknown_and_incomplete() {
+        assert!(parse(&["--verbose"]).is_err());
+        assert!(parse(&["--config"]).is_err());
+        assert!(parse(&["migrate", "a", "b"]).is_err());
+        assert!(parse(&["a"]).is_err());
+    }
+
+    #[test]
+    fn takes_the_directory_to_migrate() -> Result<()> {
+        assert_eq!(parse(&["migrate"])?.command, Some(Command::Migrate(None)));
+
+        let args = parse(&["--config", "c.toml", "migrate", "a=b"])?;
+        assert_eq!(
+            args.command,
+            Some(Command::Migrate(Some(PathBuf::from("a=b"))))
+        );
+        assert_eq!(args.config, Some(PathBuf::from("c.toml")));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use std::{rc::Rc, time::Instant};
+
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Rc<C> {
+    fn now(&self) -> Instant {
+        self.as_ref().now()
+    }
+}
+
+// time only passes when told to
+#[cfg(test)]
+pub struct ManualClock {
+    origin: Instant,
+    elapsed: std::cell::Cell<std::time::Duration>,
+}
+
+#[cfg(test)]
+impl ManualClock {
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            elapsed: Default::default(),
+        }
+    }
+
+    pub fn elapsed(&self) -> std::time::Duration {
+        self.elapsed.get()
+    }
+
+    pub fn advance(&self, duration: std::time::Duration) {
+        self.elapsed.set(self.elapsed.get() + duration);
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.origin + self.elapsed.get()
+    }
+}
@@ -0,0 +1,442 @@
+use crate::{cli::Args, grid::Geometry, layout::Layout, state::key::Bindings};
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use toml_edit::{DocumentMut, Item, Table};
+
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // where the file was loaded from, if anywhere
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+    pub screenshots: PathBuf,
+    pub recipes: PathBuf,
+    // pictures of items by ID, see `item::Library`
+    pub items: PathBuf,
+    pub font: PathBuf,
+    pub intervals: Intervals,
+    pub game: Game,
+    pub playback: Playback,
+    pub calibration: Option<Calibration>,
+    pub keys: Bindings,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Game {
+    // matched against the titles and the process names of windows
+    pub window: String,
+    // assumed to be fullscreen at this resolution while the window is not found
+    pub resolution: (i32, i32),
+    // as in options.txt: 0 for Auto, 1 to 4 for Small to Huge
+    pub gui_scale: u8,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Playback {
+    // whether to look at the slots a recipe takes from before each run and stop once one is empty
+    pub stop_when_empty: bool,
+    // whether to compare those slots with the thumbnail before each stage, and with what it left
+    // when it was recorded after it
+    pub verify: bool,
+}
+
+// in milliseconds in the file
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Intervals {
+    #[serde(with = "millis")]
+    pub left: Duration,
+    #[serde(with = "millis")]
+    pub right: Duration,
+    #[serde(with = "millis")]
+    pub space: Duration,
+    #[serde(with = "millis")]
+    pub play: Duration,
+    #[serde(with = "millis")]
+    pub double_click: Duration,
+}
+
+// overrides the layout of the crafting table while the game is at `resolution`
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Calibration {
+    pub resolution: (i32, i32),
+    pub geometry: Geometry,
+}
+
+impl Config {
+    const DIR: &str = "mctool";
+    const FILENAME: &str = "config.toml";
+
+    // the file is created with the defaults if it does not exist yet
+    pub fn load(args: &Args) -> Result<Self> {
+        let path = match &args.config {
+            Some(path) => path.clone(),
+            None => Self::default_path()?,
+        };
+
+        let mut config = if path.exists() {
+            Self::from_file(&path)?
+        } else {
+            let config = Self::default();
+            config.save(&path)?;
+            config
+        };
+
+        config.apply(args);
+        config.validate()?;
+        config.path = Some(path);
+
+        Ok(config)
+    }
+
+    pub fn default_path() -> Result<PathBuf> {
+        dirs::config_dir()
+            .map(|dir| dir.join(Self::DIR).join(Self::FILENAME))
+            .ok_or_else(|| anyhow!("config directory not found"))
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+
+        Self::from_str(&text).map_err(|e| anyhow!("{}: {e}", path.display()))
+    }
+
+    pub fn from_str(text: &str) -> Result<Self> {
+        let deserializer = toml::Deserializer::parse(text)?;
+
+        serde_path_to_error::deserialize(deserializer).map_err(|e| match e.path().to_string() {
+            path if path == "." => anyhow!("{}", e.inner().message()),
+            path => anyhow!("`{path}`: {}", e.inner().message()),
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    // only what `f` changes is written back so that CLI overrides stay out of the file. the file is
+    // edited in place, keeping the comments and the order of whatever else is in it.
+    pub fn update_file(path: impl AsRef<Path>, f: impl FnOnce(&mut Self)) -> Result<()> {
+        let path = path.as_ref();
+
+        if !path.exists() {
+            let mut config = Self::default();
+            f(&mut config);
+            return config.save(path);
+        }
+
+        let text = std::fs::read_to_string(path)?;
+        let mut config = Self::from_str(&text).map_err(|e| anyhow!("{}: {e}", path.display()))?;
+        let mut document: DocumentMut = text.parse()?;
+        let before: DocumentMut = toml::to_string_pretty(&config)?.parse()?;
+
+        f(&mut config);
+
+        let after: DocumentMut = toml::to_string_pretty(&config)?.parse()?;
+        Self::merge(document.as_table_mut(), before.as_table(), after.as_table());
+        std::fs::write(path, document.to_string())?;
+
+        Ok(())
+    }
+
+    // what differs between `before` and `after` into `document`, table by table
+    fn merge(document: &mut Table, before: &Table, after: &Table) {
+        for (key, item) in after {
+            let old = before.get(key);
+
+            match (item, old, document.get_mut(key)) {
+                (Item::Table(item), Some(Item::Table(old)), Some(Item::Table(table))) => {
+                    Self::merge(table, old, item);
+                }
+                _ if old.is_some_and(|old| old.to_string() == item.to_string()) => (),
+                // with the comments around the value
+                (Item::Value(item), _, Some(Item::Value(value))) => {
+                    let decor = value.decor().clone();
+                    *value = item.clone();
+                    *value.decor_mut() = decor;
+                }
+                _ => {
+                    document.insert(key, item.clone());
+                }
+            }
+        }
+
+        for (key, _) in before {
+            if !after.contains_key(key) {
+                document.remove(key);
+            }
+        }
+    }
+
+    fn apply(&mut self, args: &Args) {
+        if let Some(screenshots) = &args.screenshots {
+            self.screenshots = screenshots.clone();
+        }
+
+        if let Some(recipes) = &args.recipes {
+            self.recipes = recipes.clone();
+        }
+
+        if let Some(font) = &args.font {
+            self.font = font.clone();
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        let Intervals {
+            left,
+            right,
+            space,
+            play,
+            double_click,
+        } = self.intervals;
+
+        for (name, interval) in [
+            ("left", left),
+            ("right", right),
+            ("space", space),
+            ("play", play),
+            ("double_click", double_click),
+        ] {
+            if interval.is_zero() {
+                bail!("`intervals.{name}`: must be greater than 0");
+            }
+        }
+
+        let Game {
+            resolution: (width, height),
+            gui_scale,
+            ..
+        } = self.game;
+
+        if width <= 0 || height <= 0 {
+            bail!("`game.resolution`: must be greater than 0");
+        }
+
+        if Layout::MAX_GUI_SCALE < gui_scale {
+            bail!(
+                "`game.gui_scale`: must be 0 (Auto) to {}",
+                Layout::MAX_GUI_SCALE
+            );
+        }
+
+        self.keys.validate()?;
+
+        if self.recipes.is_file() {
+            bail!("`recipes`: {} is not a directory", self.recipes.display());
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        // %APPDATA%\.minecraft on Windows, ~/.minecraft elsewhere
+        let minecraft = if cfg!(windows) {
+            dirs::data_dir()
+        } else {
+            dirs::home_dir()
+        }
+        .map(|dir| dir.join(".minecraft"))
+        .unwrap_or_default();
+
+        let data = dirs::data_dir()
+            .map(|dir| dir.join(Self::DIR))
+            .unwrap_or_default();
+
+        Self {
+            path: None,
+            screenshots: minecraft.join("screenshots"),
+            recipes: data.join("recipes"),
+            items: data.join("items"),
+            font: PathBuf::from("CascadiaMono.ttf"),
+            intervals: Intervals::default(),
+            game: Game::default(),
+            playback: Playback::default(),
+            calibration: None,
+            keys: Bindings::default(),
+        }
+    }
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            stop_when_empty: false,
+            verify: true,
+        }
+    }
+}
+
+impl Default for Intervals {
+    fn default() -> Self {
+        Self {
+            left: Duration::from_millis(10),
+            right: Duration::from_millis(10),
+            space: Duration::from_millis(50),
+            play: Duration::from_millis(7),
+            double_click: Duration::from_millis(50),
+        }
+    }
+}
+
+// 1920x1080 with "Large" GUIs
+impl Default for Game {
+    fn default() -> Self {
+        Self {
+            window: "Minecraft".to_string(),
+            resolution: (1920, 1080),
+            gui_scale: 3,
+        }
+    }
+}
+
+pub mod millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(value.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serializer};
+        use std::time::Duration;
+
+        pub fn serialize<S: Serializer>(
+            value: &Option<Duration>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match value {
+                Some(value) => super::serialize(value, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Duration>, D::Error> {
+            Option::<u64>::deserialize(deserializer).map(|value| value.map(Duration::from_millis))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_fields_fall_back_to_defaults() -> Result<()> {
+        let config = Config::from_str(
+            r#"
+            recipes = "/tmp/recipes"
+
+            [intervals]
+            play = 20
+            "#,
+        )?;
+
+        assert_eq!(config.recipes, Path::new("/tmp/recipes"));
+        assert_eq!(config.intervals.play, Duration::from_millis(20));
+        assert_eq!(config.intervals.left, Intervals::default().left);
+
+        Ok(())
+    }
+
+    #[test]
+    fn errors_name_the_field() {
+        let message = |text| Config::from_str(text).err().unwrap().to_string();
+
+        assert!(message("[intervals]\nleft = \"fast\"").starts_with("`intervals.left`: "));
+        assert!(message("font = 1").starts_with("`font`: "));
+        assert!(message("[intervals]\nmiddle = 1").contains("middle"));
+        assert_eq!(
+            message("[keys]\nlock = \"LCtrl+Hyper\""),
+            "`keys.lock`: unknown key name `Hyper`"
+        );
+
+        let mut config = Config::default();
+        config.intervals.double_click = Duration::ZERO;
+        assert_eq!(
+            config.validate().err().unwrap().to_string(),
+            "`intervals.double_click`: must be greater than 0"
+        );
+
+        let mut config = Config::default();
+        config.game.gui_scale = 5;
+        assert_eq!(
+            config.validate().err().unwrap().to_string(),
+            "`game.gui_scale`: must be 0 (Auto) to 4"
+        );
+    }
+
+    #[test]
+    fn round_trips_through_file() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join("nested").join(Config::FILENAME);
+
+        let mut config = Config::default();
+        config.intervals.space = Duration::from_millis(42);
+        config.save(&path)?;
+
+        assert_eq!(
+            Config::from_file(&path)?.intervals.space,
+            Duration::from_millis(42)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn updates_the_file_in_place() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join(Config::FILENAME);
+        let text = r#"# where the game saves them
+screenshots = "/tmp/screenshots"
+
+[intervals]
+play = 20 # slower
+left = 30
+
+[keys]
+# the usual one
+play = "G"
+"#;
+        std::fs::write(&path, text)?;
+
+        Config::update_file(&path, |config| {
+            config.intervals.left = Duration::from_millis(40);
+            config.keys = Bindings::default();
+            config.keys.play = "H".parse().unwrap();
+        })?;
+
+        assert_eq!(
+            std::fs::read_to_string(&path)?,
+            text.replace("left = 30", "left = 40")
+                .replace("play = \"G\"", "play = \"H\"")
+        );
+
+        Ok(())
+    }
+}
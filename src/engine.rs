@@ -1,9 +1,16 @@
 use crate::{
-    io,
+    coord::Coord,
+    grid::{Geometry, Grid, Picture},
+    io::{self, Frames},
     map_err_anyhow::MapErrAnyhow,
+    recipe::{Mark, Recipe},
     resources::Fonts,
     resources::Textures,
-    state::{State, detail::Detail},
+    state::{
+        State,
+        detail::{Detail, Repeat},
+        key::{Capture, Chord},
+    },
 };
 use anyhow::Result;
 use sdl2::{
@@ -16,7 +23,7 @@ use sdl2::{
     ttf::Font,
     video::{Window, WindowContext},
 };
-use std::time::Duration;
+use std::{path::Path, time::Duration};
 
 pub struct Engine {
     frame_initialized: bool,
@@ -32,7 +39,7 @@ impl Engine {
     const WIDTH: u32 = io::INV_WIDTH + io::ITEM_WIDTH + Self::PADDING * 3;
     const HEIGHT: u32 = io::INV_HEIGHT + Self::PADDING * 3 + Self::TAB_HEIGHT + Self::PATH_HEIGHT;
     const CENTER: (i32, i32) = (Self::WIDTH as i32 / 2, Self::HEIGHT as i32 / 2);
-    const TAB_WIDTH: u32 = 110;
+    const TAB_WIDTH: u32 = Self::WIDTH / 7;
     const TAB_HEIGHT: u32 = 24;
     const PATH_HEIGHT: u32 = 6;
     const POLLING_RATE: Duration = Duration::from_millis(1);
@@ -40,7 +47,9 @@ impl Engine {
     const TAB_BACKGROUND: Color = Color::RGB(0x38, 0x38, 0x38);
     const GREEN: Color = Color::RGB(0x00, 0x7F, 0x00);
     const RED: Color = Color::RGB(0x7F, 0x00, 0x00);
+    const BINDING_HEIGHT: u32 = 24;
     const DIM: Color = Color::RGBA(0x00, 0x00, 0x00, 0xC0);
+    const MISMATCH: Color = Color::RGBA(0xFF, 0x00, 0x00, 0x80);
 
     pub fn new() -> Result<Self> {
         let context = sdl2::init().map_err_anyhow()?;
@@ -72,11 +81,34 @@ impl Engine {
         self.canvas.texture_creator()
     }
 
+    // bounding box of every display, which is what absolute pointing devices are mapped onto
+    pub fn desktop_size(&self) -> Result<(i32, i32)> {
+        let displays = self.video.num_video_displays().map_err_anyhow()?;
+        let mut size = (0, 0);
+
+        for index in 0..displays {
+            let bounds = self.video.display_bounds(index).map_err_anyhow()?;
+            size.0 = size.0.max(bounds.right());
+            size.1 = size.1.max(bounds.bottom());
+        }
+
+        Ok(size)
+    }
+
+    pub const fn frame_initialized(&self) -> bool {
+        self.frame_initialized
+    }
+
     pub fn poll_event(&mut self) -> Option<Event> {
         self.event_pump.poll_event()
     }
 
-    pub fn draw(&mut self, state: &State, fonts: &Fonts) -> Result<()> {
+    pub fn draw(
+        &mut self,
+        state: &State,
+        fonts: &Fonts,
+        textures: Option<&Textures>,
+    ) -> Result<()> {
         let draw = state.draw_required() || !self.frame_initialized;
 
         if draw {
@@ -116,23 +148,41 @@ impl Engine {
             tab(1, "LEFT", state.spam_left.is_active(), false)?;
             tab(2, "RIGHT", state.spam_right.is_active(), false)?;
             tab(3, "SPACE", state.spam_space.is_active(), false)?;
+            tab(4, state.container().name(), false, false)?;
+            tab(
+                5,
+                &format!("x{}", state.speed()),
+                state.speed() != 1.0,
+                false,
+            )?;
 
             self.draw_lock(state, fonts)?;
-            self.draw_thumbnail(state, fonts)?;
+            self.draw_thumbnail(state, fonts, textures)?;
 
             match state.detail() {
                 Detail::Idle => (),
-                Detail::Recording { clicks, count } => {
-                    let display = match clicks.last() {
+                Detail::Recording {
+                    steps,
+                    count,
+                    timing,
+                    frames,
+                    ..
+                } => {
+                    let display = match steps.last() {
                         None => "Recording...".to_string(),
                         Some(last) if count < &2 => format!("[..., {last:?}]"),
                         Some(last) => format!("[..., {last:?} * {count}]"),
                     };
+                    let display = match timing {
+                        None => display,
+                        Some(_) => format!("{display} (timed)"),
+                    };
 
                     self.dim()?;
                     self.draw_font_centered(&fonts.large, &display, Self::CENTER, Color::WHITE)?;
+                    self.draw_screenshots(fonts, frames)?;
                 }
-                Detail::Naming { name, .. } => {
+                Detail::Naming { name, frames, .. } => {
                     self.dim()?;
                     self.draw_font_centered(
                         &fonts.large,
@@ -140,16 +190,145 @@ impl Engine {
                         Self::CENTER,
                         Color::WHITE,
                     )?;
+                    self.draw_screenshots(fonts, frames)?;
+                }
+                Detail::Playing {
+                    stages,
+                    stage,
+                    progress,
+                    dry_run,
+                } => {
+                    let run = progress.run;
+                    let (display, progress) = match stages.get(*stage) {
+                        Some(schedule) if 1 < stages.len() => (
+                            format!(
+                                "Playing {} ({}/{})...",
+                                schedule.name,
+                                stage + 1,
+                                stages.len()
+                            ),
+                            schedule.repeat,
+                        ),
+                        Some(schedule) => ("Playing...".to_string(), schedule.repeat),
+                        None => ("Playing...".to_string(), Repeat::Times(1)),
+                    };
+                    let display = match progress {
+                        Repeat::Times(1) => display,
+                        Repeat::Times(times) => format!("{display} {run}/{times}"),
+                        Repeat::UntilAbort => format!("{display} {run} (until aborted)"),
+                    };
+
+                    self.dim()?;
+
+                    match dry_run {
+                        None => self.draw_font_centered(
+                            &fonts.large,
+                            &display,
+                            Self::CENTER,
+                            Color::WHITE,
+                        )?,
+                        Some(dry_run) => {
+                            self.draw_font_centered(
+                                &fonts.large,
+                                &format!("Dry run: {display}"),
+                                (Self::WIDTH as i32 / 2, Self::PADDING as i32),
+                                Color::WHITE,
+                            )?;
+
+                            for (i, line) in dry_run.lines.iter().enumerate() {
+                                self.draw_font_left(
+                                    &fonts.regular,
+                                    line,
+                                    (
+                                        Self::PADDING as i32,
+                                        (Self::PADDING * 2) as i32
+                                            + (Self::BINDING_HEIGHT as i32 * i as i32),
+                                    ),
+                                    Color::WHITE,
+                                )?;
+                            }
+                        }
+                    }
+                }
+                Detail::Mismatched { slots, .. } => {
+                    let slots: Vec<_> = slots.iter().map(|grid| format!("{grid:?}")).collect();
+
+                    self.draw_font_centered(
+                        &fonts.regular,
+                        &format!(
+                            "Unlike the thumbnail: {} (confirm to play anyway)",
+                            slots.join(", ")
+                        ),
+                        (Self::WIDTH as i32 / 2, Self::PADDING as i32 / 2),
+                        Color::WHITE,
+                    )?;
                 }
-                Detail::Playing { .. } => {
+                Detail::Repeating { count, .. } => {
                     self.dim()?;
                     self.draw_font_centered(
                         &fonts.large,
-                        "Playing...",
+                        &format!("Repeat: [{count}] times (empty: until aborted)"),
                         Self::CENTER,
                         Color::WHITE,
                     )?;
                 }
+                Detail::Choosing {
+                    playlists,
+                    selected,
+                } => {
+                    self.dim()?;
+
+                    match playlists.get(*selected) {
+                        None => self.draw_font_centered(
+                            &fonts.large,
+                            "no playlists found",
+                            Self::CENTER,
+                            Color::WHITE,
+                        )?,
+                        Some((name, playlist)) => {
+                            let (x, y) = Self::CENTER;
+
+                            self.draw_font_centered(
+                                &fonts.large,
+                                &format!("◀ {name} [{}/{}] ▶", selected + 1, playlists.len()),
+                                (x, y - Self::BINDING_HEIGHT as i32 / 2),
+                                Color::WHITE,
+                            )?;
+                            self.draw_font_centered(
+                                &fonts.regular,
+                                &playlist.to_string(),
+                                (x, y + Self::BINDING_HEIGHT as i32 / 2),
+                                Color::WHITE,
+                            )?;
+                        }
+                    }
+                }
+                Detail::Editing {
+                    dir,
+                    recipe,
+                    selected,
+                    edited,
+                } => {
+                    self.dim()?;
+                    self.draw_steps(fonts, dir, recipe, *selected, *edited)?;
+                }
+                Detail::Previewing {
+                    recipe, current, ..
+                } => {
+                    let display = match recipe.steps().nth(*current) {
+                        Some(step) => {
+                            format!("Step {}/{}: {step:?}", current + 1, recipe.steps.len())
+                        }
+                        None => "Previewing...".to_string(),
+                    };
+
+                    self.draw_font_centered(
+                        &fonts.regular,
+                        &display,
+                        (Self::WIDTH as i32 / 2, Self::PADDING as i32 / 2),
+                        Color::WHITE,
+                    )?;
+                }
                 Detail::TradingFirst { .. } | Detail::TradingSecond { .. } => {
                     self.dim()?;
                     self.draw_font_centered(
@@ -168,6 +347,15 @@ impl Engine {
                         Color::WHITE,
                     )?;
                 }
+                Detail::Learning { name, .. } => {
+                    self.dim()?;
+                    self.draw_font_centered(
+                        &fonts.large,
+                        &format!("Item in the slot: [{name}]"),
+                        Self::CENTER,
+                        Color::WHITE,
+                    )?;
+                }
                 Detail::Renaming { name, .. } => {
                     self.dim()?;
                     self.draw_font_centered(
@@ -177,6 +365,43 @@ impl Engine {
                         Color::WHITE,
                     )?;
                 }
+                Detail::Calibrating { points } => {
+                    self.dim()?;
+                    self.draw_font_centered(
+                        &fonts.large,
+                        &format!(
+                            "Click {} ({}/{})",
+                            Geometry::CALIBRATION[points.len()],
+                            points.len() + 1,
+                            Geometry::CALIBRATION.len()
+                        ),
+                        Self::CENTER,
+                        Color::WHITE,
+                    )?;
+                }
+                Detail::Validating { slots, current, .. } => {
+                    let display = match current.and_then(|index| slots.get(index)) {
+                        Some(grid) => format!("Validating: {grid:?}"),
+                        None => "Validating...".to_string(),
+                    };
+
+                    self.dim()?;
+                    self.draw_font_centered(&fonts.large, &display, Self::CENTER, Color::WHITE)?;
+                }
+                Detail::Rebinding {
+                    selected,
+                    capture,
+                    conflict,
+                } => {
+                    self.dim()?;
+                    self.draw_bindings(
+                        state,
+                        fonts,
+                        *selected,
+                        capture.as_ref(),
+                        conflict.as_ref(),
+                    )?;
+                }
             }
 
             self.canvas.present();
@@ -231,15 +456,178 @@ impl Engine {
         )
     }
 
-    fn draw_thumbnail(&mut self, state: &State, fonts: &Fonts) -> Result<()> {
-        match &state.recipes.textures() {
+    // which of the screenshots taken while recording have arrived, if they are watched for
+    fn draw_screenshots(&mut self, fonts: &Fonts, frames: &Frames) -> Result<()> {
+        let Some(screenshots) = &frames.screenshots else {
+            return Ok(());
+        };
+
+        let status = |i: usize| match i < screenshots.len() {
+            true => "arrived",
+            false => "waiting (F2)",
+        };
+
+        self.draw_font_centered(
+            &fonts.regular,
+            &format!("Inventory: {}, Item: {}", status(0), status(1)),
+            (
+                Self::WIDTH as i32 / 2,
+                Self::CENTER.1 + Self::BINDING_HEIGHT as i32,
+            ),
+            Color::WHITE,
+        )
+    }
+
+    // the steps around the selected one below the name of the recipe
+    fn draw_steps(
+        &mut self,
+        fonts: &Fonts,
+        dir: &Path,
+        recipe: &Recipe,
+        selected: usize,
+        edited: bool,
+    ) -> Result<()> {
+        let rows = ((Self::HEIGHT - Self::PADDING) / Self::BINDING_HEIGHT) as usize - 1;
+        let first = selected
+            .saturating_sub(rows / 2)
+            .min(recipe.steps.len().saturating_sub(rows));
+        let row = |i: usize| Self::PADDING as i32 / 2 + (i * Self::BINDING_HEIGHT as usize) as i32;
+        let name = dir.file_name().unwrap_or_default().to_string_lossy();
+
+        self.draw_font_left(
+            &fonts.regular,
+            &format!(
+                "Editing: {name}{} ({} steps)",
+                if edited { " *" } else { "" },
+                recipe.steps.len()
+            ),
+            (
+                Self::PADDING as i32 / 2,
+                row(0) + Self::BINDING_HEIGHT as i32 / 2,
+            ),
+            Color::WHITE,
+        )?;
+
+        for (i, entry) in recipe.steps.iter().enumerate().skip(first).take(rows) {
+            let y = row(i - first + 1);
+
+            if i == selected {
+                self.draw_rect(
+                    Rect::new(0, y, Self::WIDTH, Self::BINDING_HEIGHT),
+                    Self::GREEN,
+                )?;
+            }
+
+            let delay = match entry.delay.as_millis() {
+                0 => String::new(),
+                delay => format!(" after {delay}ms"),
+            };
+
+            self.draw_font_left(
+                &fonts.regular,
+                &format!("{}. {:?}{delay}", i + 1, entry.step),
+                (
+                    Self::PADDING as i32 / 2,
+                    y + Self::BINDING_HEIGHT as i32 / 2,
+                ),
+                Color::WHITE,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // two columns of `action: keys`, where the selected one shows the keys being held while capturing
+    fn draw_bindings(
+        &mut self,
+        state: &State,
+        fonts: &Fonts,
+        selected: usize,
+        capture: Option<&Capture>,
+        conflict: Option<&Chord>,
+    ) -> Result<()> {
+        let width = Self::WIDTH / 2;
+        let height = Self::BINDING_HEIGHT;
+        // two columns a page, turned to the one of the selected binding, with the bottom left for
+        // the number of the page
+        let rows = ((Self::HEIGHT - Self::PADDING * 2) / height) as usize;
+        let first = selected / (rows * 2) * (rows * 2);
+        let pages = state.bindings().iter().count().div_ceil(rows * 2);
+
+        for (i, (name, chord)) in state
+            .bindings()
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(rows * 2)
+        {
+            let x = ((i - first) / rows) as i32 * width as i32;
+            let y = Self::PADDING as i32 / 2 + ((i - first) % rows) as i32 * height as i32;
+
+            let is_selected = i == selected;
+            let is_conflicting =
+                conflict.is_some_and(|conflict| is_selected || conflict.conflicts_with(chord));
+
+            let keys = match (is_selected, capture, conflict) {
+                (true, Some(capture), _) if capture.held().is_empty() => "...".to_string(),
+                (true, Some(capture), _) => Chord::new(capture.held()).to_string(),
+                (true, None, Some(conflict)) => conflict.to_string(),
+                _ => chord.to_string(),
+            };
+
+            if is_conflicting || is_selected {
+                self.draw_rect(
+                    Rect::new(x, y, width, height),
+                    if is_conflicting {
+                        Self::RED
+                    } else {
+                        Self::GREEN
+                    },
+                )?;
+            }
+
+            self.draw_font_left(
+                &fonts.regular,
+                &format!("{name}: {keys}"),
+                (x + Self::PADDING as i32 / 2, y + height as i32 / 2),
+                Color::WHITE,
+            )?;
+        }
+
+        if 1 < pages {
+            self.draw_font_centered(
+                &fonts.regular,
+                &format!("Page {}/{pages}", first / (rows * 2) + 1),
+                (
+                    Self::WIDTH as i32 / 2,
+                    (Self::HEIGHT - Self::PADDING / 2) as i32,
+                ),
+                Color::WHITE,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn draw_thumbnail(
+        &mut self,
+        state: &State,
+        fonts: &Fonts,
+        textures: Option<&Textures>,
+    ) -> Result<()> {
+        match textures {
             None => self.draw_font_centered(
                 &fonts.large,
                 &state.recipes.to_string(),
                 Self::CENTER,
                 Color::WHITE,
             ),
-            Some(Textures { thumbnail, item }) => {
+            Some(Textures {
+                thumbnail,
+                item,
+                recipe,
+                ingredients,
+            }) => {
                 self.draw_font_centered(
                     &fonts.large,
                     &state.recipes.to_string(),
@@ -272,11 +660,161 @@ impl Engine {
                     self.canvas.copy(item, None, dst).map_err_anyhow()?;
                 }
 
-                Ok(())
+                // where nothing else is shown, in a line below the name
+                if matches!(state.detail(), Detail::Idle) && !ingredients.is_empty() {
+                    let ingredients: Vec<_> = ingredients
+                        .iter()
+                        .map(|stack| {
+                            let item = stack.item.as_deref().unwrap_or("?");
+                            format!("{item} x{}", stack.count)
+                        })
+                        .collect();
+                    // as many as fit, and how many others there are
+                    let width = Self::WIDTH - Self::PADDING * 2;
+                    let line = (1..=ingredients.len())
+                        .rev()
+                        .map(|shown| match ingredients.len() - shown {
+                            0 => ingredients.join(", "),
+                            rest => format!("{}, +{rest} more", ingredients[..shown].join(", ")),
+                        })
+                        .find(|line| fonts.regular.size_of(line).is_ok_and(|(w, _)| w <= width))
+                        .unwrap_or_else(|| format!("{} ingredients", ingredients.len()));
+                    let top = Self::PADDING as i32
+                        + Self::PATH_HEIGHT as i32 / 2
+                        + fonts.large.height() / 2;
+
+                    self.draw_font_centered(
+                        &fonts.regular,
+                        &line,
+                        (Self::WIDTH as i32 / 2, top + fonts.regular.height() / 2),
+                        Color::WHITE,
+                    )?;
+                }
+
+                match state.detail() {
+                    Detail::Previewing {
+                        recipe, current, ..
+                    } => self.draw_marks(fonts, recipe, Some(*current)),
+                    // as edited so far, rather than as saved
+                    Detail::Editing {
+                        recipe, selected, ..
+                    } => self.draw_marks(fonts, recipe, Some(*selected)),
+                    Detail::Mismatched { slots, .. } => {
+                        let container = state.container();
+                        let size = (io::INV_WIDTH / 9) as i32;
+
+                        for grid in slots {
+                            if let Some(Coord(x, y)) = grid.picture(
+                                container,
+                                Picture::Thumbnail,
+                                Coord(io::INV_WIDTH as i32, io::INV_HEIGHT as i32),
+                            ) {
+                                self.draw_rect(
+                                    Rect::new(
+                                        Self::PADDING as i32 + x - size / 2,
+                                        (Self::PATH_HEIGHT + Self::PADDING * 2) as i32 + y
+                                            - size / 2,
+                                        size as u32,
+                                        size as u32,
+                                    ),
+                                    Self::MISMATCH,
+                                )?;
+                            }
+                        }
+
+                        Ok(())
+                    }
+                    _ => match recipe {
+                        Some(recipe) => self.draw_marks(fonts, recipe, None),
+                        None => Ok(()),
+                    },
+                }
             }
         }
     }
 
+    // numbered by step over the pictures, or in a line below them for the slots which are on neither
+    fn draw_marks(&mut self, fonts: &Fonts, recipe: &Recipe, current: Option<usize>) -> Result<()> {
+        let thumbnail = Coord(
+            Self::PADDING as i32,
+            (Self::PATH_HEIGHT + Self::PADDING * 2) as i32,
+        );
+        let item = Coord(
+            Self::WIDTH as i32 - Self::PADDING as i32 - io::ITEM_WIDTH as i32,
+            thumbnail.1 + io::INV_HEIGHT as i32 / 2 - io::ITEM_HEIGHT as i32 / 2,
+        );
+
+        let mut slots: Vec<(Grid, Vec<Mark>)> = Vec::new();
+        for mark in recipe.marks() {
+            match slots.iter_mut().find(|(grid, _)| *grid == mark.grid) {
+                Some((_, marks)) => marks.push(mark),
+                None => slots.push((mark.grid, vec![mark])),
+            }
+        }
+
+        let mut others = Vec::new();
+
+        for (grid, marks) in slots {
+            let label = marks
+                .iter()
+                .map(|mark| match mark.count {
+                    1 => format!("{}", mark.first + 1),
+                    count => format!("{}*{count}", mark.first + 1),
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            let is_current = current.is_some_and(|current| {
+                marks
+                    .iter()
+                    .any(|mark| (mark.first..mark.first + mark.count).contains(&current))
+            });
+
+            let size = |width, height| Coord(width as i32, height as i32);
+            let at = grid
+                .picture(
+                    recipe.container,
+                    Picture::Thumbnail,
+                    size(io::INV_WIDTH, io::INV_HEIGHT),
+                )
+                .map(|at| at.add(thumbnail))
+                .or_else(|| {
+                    grid.picture(
+                        recipe.container,
+                        Picture::Item,
+                        size(io::ITEM_WIDTH, io::ITEM_HEIGHT),
+                    )
+                    .map(|at| at.add(item))
+                });
+
+            let Some(Coord(x, y)) = at else {
+                others.push(format!("{grid:?} {label}"));
+                continue;
+            };
+
+            let surface = fonts.regular.render(&label).blended(Color::WHITE)?;
+            let (width, height) = (surface.width() + 4, surface.height());
+            self.draw_rect(
+                Rect::new(x - width as i32 / 2, y - height as i32 / 2, width, height),
+                if is_current { Self::GREEN } else { Self::DIM },
+            )?;
+            self.draw_surface(surface, (x - width as i32 / 2 + 2, y - height as i32 / 2))?;
+        }
+
+        if !others.is_empty() {
+            self.draw_font_centered(
+                &fonts.regular,
+                &others.join("  "),
+                (
+                    Self::WIDTH as i32 / 2,
+                    thumbnail.1 + io::INV_HEIGHT as i32 + Self::PADDING as i32 / 2,
+                ),
+                Color::WHITE,
+            )?;
+        }
+
+        Ok(())
+    }
+
     fn draw_font_centered(
         &mut self,
         font: &Font,
@@ -294,6 +832,19 @@ impl Engine {
         self.draw_surface(surface, center)
     }
 
+    fn draw_font_left(
+        &mut self,
+        font: &Font,
+        text: &str,
+        (x, y): (i32, i32),
+        color: Color,
+    ) -> Result<()> {
+        let surface = font.render(text).blended(color)?;
+        let y = y - surface.height() as i32 / 2;
+
+        self.draw_surface(surface, (x, y))
+    }
+
     fn draw_surface(&mut self, surface: Surface, (x, y): (i32, i32)) -> Result<()> {
         let tex_creator = self.canvas.texture_creator();
         let texture = surface.as_texture(&tex_creator)?;
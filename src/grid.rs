@@ -1,71 +1,565 @@
-use crate::coord::Coord;
-use anyhow::Result;
+use crate::{coord::Coord, io::InputBackend, layout::Layout};
+use anyhow::{Result, anyhow, bail};
+use image::RgbImage;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Grid {
     Table(u8, u8),
     Craft,
     Inv(u8, u8),
     Hotbar(u8),
+    Chest(u8, u8),
+    Input(u8),
+    Fuel,
+    Bottle(u8),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Container {
+    #[default]
+    CraftingTable,
+    Inventory,
+    Furnace,
+    Chest,
+    LargeChest,
+    Anvil,
+    BrewingStand,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Kind {
+    Table,
+    Craft,
+    Inv,
+    Hotbar,
+    Chest,
+    Input,
+    Fuel,
+    Bottle,
+}
+
+// a rectangle of slots, including their borders. `first` is the number of the leftmost one for the
+// kinds which number their slots (e.g. the second input of the anvil is on its own).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Region {
+    pub kind: Kind,
+    pub first: u8,
+    pub origin: Coord,
+    pub columns: u8,
+    pub rows: u8,
+}
+
+// the pictures saved with a recipe
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Picture {
+    // of the inventory with the hotbar
+    Thumbnail,
+    Item,
+}
+
+// where the slots of a container are in pixels
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Geometry {
+    pub slot: Coord,
+    pub regions: Box<[Region]>,
 }
 
 impl Grid {
-    const GRID_SIZE: Coord = Coord(54, 54);
-    const ORIGIN_TABLE: Coord = Coord(783, 339);
-    const ORIGIN_CRAFT: Coord = Coord(1065, 393);
-    const ORIGIN_INV: Coord = Coord(717, 540);
-    const ORIGIN_HOTBAR: Coord = Coord(717, 714);
-    const END_TABLE: Coord = Self::ORIGIN_TABLE.add(Self::GRID_SIZE.mul(3));
-    const END_CRAFT: Coord = Self::ORIGIN_CRAFT.add(Self::GRID_SIZE);
-    const END_INV: Coord = Self::ORIGIN_INV.add(Self::GRID_SIZE.emul(9, 3));
-    const END_HOTBAR: Coord = Self::ORIGIN_HOTBAR.add(Self::GRID_SIZE.emul(9, 1));
-
-    pub fn set_cursor(&self) -> Result<()> {
-        let Coord(x, y) = self.into();
-        crate::io::set_cursor(x, y)
+    const fn new(kind: Kind, x: u8, y: u8) -> Self {
+        match kind {
+            Kind::Table => Self::Table(x, y),
+            Kind::Craft => Self::Craft,
+            Kind::Inv => Self::Inv(x, y),
+            Kind::Hotbar => Self::Hotbar(x),
+            Kind::Chest => Self::Chest(x, y),
+            Kind::Input => Self::Input(x),
+            Kind::Fuel => Self::Fuel,
+            Kind::Bottle => Self::Bottle(x),
+        }
+    }
+
+    const fn kind(&self) -> (Kind, u8, u8) {
+        match *self {
+            Self::Table(x, y) => (Kind::Table, x, y),
+            Self::Craft => (Kind::Craft, 0, 0),
+            Self::Inv(x, y) => (Kind::Inv, x, y),
+            Self::Hotbar(x) => (Kind::Hotbar, x, 0),
+            Self::Chest(x, y) => (Kind::Chest, x, y),
+            Self::Input(x) => (Kind::Input, x, 0),
+            Self::Fuel => (Kind::Fuel, 0, 0),
+            Self::Bottle(x) => (Kind::Bottle, x, 0),
+        }
+    }
+
+    pub fn at(coord: Coord, geometry: &Geometry) -> Option<Self> {
+        geometry.regions.iter().find_map(|region| {
+            let end = region.origin.add(
+                geometry
+                    .slot
+                    .emul(region.columns.into(), region.rows.into()),
+            );
+
+            coord.is_contained(region.origin, end).then(|| {
+                let Coord(x, y) = coord.sub(region.origin).ediv(geometry.slot);
+                Self::new(region.kind, region.first + x as u8, y as u8)
+            })
+        })
+    }
+
+    // the centre of the slot, if the container has it
+    pub fn coord(&self, geometry: &Geometry) -> Option<Coord> {
+        let (kind, x, y) = self.kind();
+
+        geometry
+            .regions
+            .iter()
+            .find(|region| {
+                region.kind == kind
+                    && (region.first..region.first + region.columns).contains(&x)
+                    && y < region.rows
+            })
+            .map(|region| {
+                region
+                    .origin
+                    .add(geometry.slot.emul((x - region.first).into(), y.into()))
+                    .add(geometry.slot.div(2))
+            })
+    }
+
+    pub fn set_cursor(&self, geometry: &Geometry, backend: &dyn InputBackend) -> Result<()> {
+        let Coord(x, y) = self
+            .coord(geometry)
+            .ok_or_else(|| anyhow!("{self:?} is not in this container"))?;
+
+        backend.set_cursor(x, y)
     }
-}
 
-impl TryFrom<Coord> for Grid {
-    type Error = &'static str;
+    // the middle of the slot, away from the border and the stack size, as (origin, size)
+    pub fn inner(&self, geometry: &Geometry) -> Option<(Coord, Coord)> {
+        let size = geometry.slot.div(2);
+        let centre = self.coord(geometry)?;
 
-    fn try_from(value: Coord) -> Result<Self, Self::Error> {
-        let f = |origin| {
-            let Coord(x, y) = value.sub(origin).ediv(Self::GRID_SIZE);
-            (x as u8, y as u8)
+        Some((centre.sub(size.div(2)), size))
+    }
+
+    // the centre of the slot within the picture it is on, if any, with the picture scaled to `size`
+    pub fn picture(&self, container: Container, picture: Picture, size: Coord) -> Option<Coord> {
+        // only the proportions matter, which are the same at every integral scale
+        let layout = Layout::new((1920, 1080), 3);
+        let centre = self.coord(&Geometry::new(&layout, container))?;
+        let (origin, crop) = match picture {
+            Picture::Thumbnail => Geometry::new(&layout, container).player()?,
+            Picture::Item => {
+                let layout = layout.container(container.size());
+                let (origin, crop) = container.item();
+                (layout.screen(origin), crop.mul(layout.scale()))
+            }
         };
 
-        if value.is_contained(Self::ORIGIN_TABLE, Self::END_TABLE) {
-            let (x, y) = f(Self::ORIGIN_TABLE);
-            Ok(Self::Table(x, y))
-        } else if value.is_contained(Self::ORIGIN_CRAFT, Self::END_CRAFT) {
-            Ok(Self::Craft)
-        } else if value.is_contained(Self::ORIGIN_INV, Self::END_INV) {
-            let (x, y) = f(Self::ORIGIN_INV);
-            Ok(Self::Inv(x, y))
-        } else if value.is_contained(Self::ORIGIN_HOTBAR, Self::END_HOTBAR) {
-            let (x, _) = f(Self::ORIGIN_HOTBAR);
-            Ok(Self::Hotbar(x))
-        } else {
-            Err("not on a grid")
+        centre
+            .is_contained(origin, origin.add(crop))
+            .then(|| centre.sub(origin).emul(size.0, size.1).ediv(crop))
+    }
+}
+
+// whether nothing is drawn over the grey background of a slot
+pub fn is_empty(image: &RgbImage) -> bool {
+    const BACKGROUND: i32 = 139;
+    const TOLERANCE: i32 = 12;
+
+    let background = image
+        .pixels()
+        .filter(|pixel| {
+            pixel
+                .0
+                .iter()
+                .all(|&c| (i32::from(c) - BACKGROUND).abs() <= TOLERANCE)
+        })
+        .count();
+
+    let total = image.width() as usize * image.height() as usize;
+
+    // a few pixels of an item may be as grey as the background
+    0 < total && total * 9 <= background * 10
+}
+
+impl Container {
+    pub const ALL: &[Self] = &[
+        Self::CraftingTable,
+        Self::Inventory,
+        Self::Furnace,
+        Self::Chest,
+        Self::LargeChest,
+        Self::Anvil,
+        Self::BrewingStand,
+    ];
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::CraftingTable => "TABLE",
+            Self::Inventory => "INVENTORY",
+            Self::Furnace => "FURNACE",
+            Self::Chest => "CHEST",
+            Self::LargeChest => "LARGE CHEST",
+            Self::Anvil => "ANVIL",
+            Self::BrewingStand => "BREWING",
         }
     }
+
+    pub fn has(&self, grid: Grid) -> bool {
+        let (kind, x, y) = grid.kind();
+
+        self.regions().iter().any(|region| {
+            region.kind == kind
+                && (region.first..region.first + region.columns).contains(&x)
+                && y < region.rows
+        })
+    }
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|c| c == self).unwrap_or_default();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    // in GUI units
+    pub const fn size(&self) -> Coord {
+        match self {
+            Self::Chest => Coord(176, 168),
+            Self::LargeChest => Coord(176, 222),
+            _ => Layout::CONTAINER,
+        }
+    }
+
+    // what the thumbnail of the resulting item is cropped from, as (origin, size) in GUI units
+    pub const fn item(&self) -> (Coord, Coord) {
+        match self {
+            Self::CraftingTable => (Coord(119, 30), Coord(26, 26)),
+            Self::Inventory => (Coord(143, 35), Coord(18, 18)),
+            Self::Furnace => (Coord(111, 30), Coord(26, 26)),
+            Self::Chest | Self::LargeChest => (Coord(7, 17), Coord(18, 18)),
+            Self::Anvil => (Coord(133, 46), Coord(18, 18)),
+            Self::BrewingStand => (Coord(78, 16), Coord(18, 18)),
+        }
+    }
+
+    // in GUI units, as laid out in 1.8.9
+    fn regions(&self) -> Vec<Region> {
+        let region = |kind, first, (x, y), columns, rows| Region {
+            kind,
+            first,
+            origin: Coord(x, y),
+            columns,
+            rows,
+        };
+        let slot = |kind, first, origin| region(kind, first, origin, 1, 1);
+        // the player's inventory, which is lower in the chests
+        let player = |y| {
+            [
+                region(Kind::Inv, 0, (7, y), 9, 3),
+                region(Kind::Hotbar, 0, (7, y + 58), 9, 1),
+            ]
+        };
+
+        let mut regions = match self {
+            Self::CraftingTable => vec![
+                region(Kind::Table, 0, (29, 16), 3, 3),
+                slot(Kind::Craft, 0, (123, 34)),
+            ],
+            Self::Inventory => vec![
+                region(Kind::Table, 0, (87, 25), 2, 2),
+                slot(Kind::Craft, 0, (143, 35)),
+            ],
+            Self::Furnace => vec![
+                slot(Kind::Input, 0, (55, 16)),
+                slot(Kind::Fuel, 0, (55, 52)),
+                slot(Kind::Craft, 0, (115, 34)),
+            ],
+            Self::Chest => vec![region(Kind::Chest, 0, (7, 17), 9, 3)],
+            Self::LargeChest => vec![region(Kind::Chest, 0, (7, 17), 9, 6)],
+            Self::Anvil => vec![
+                slot(Kind::Input, 0, (26, 46)),
+                slot(Kind::Input, 1, (75, 46)),
+                slot(Kind::Craft, 0, (133, 46)),
+            ],
+            Self::BrewingStand => vec![
+                slot(Kind::Bottle, 0, (55, 45)),
+                slot(Kind::Bottle, 1, (78, 52)),
+                slot(Kind::Bottle, 2, (101, 45)),
+                slot(Kind::Input, 0, (78, 16)),
+            ],
+        };
+
+        regions.extend(match self {
+            Self::Chest => player(84),
+            Self::LargeChest => player(138),
+            _ => player(83),
+        });
+
+        regions
+    }
 }
 
-impl From<&Grid> for Coord {
-    fn from(value: &Grid) -> Self {
-        let f = |origin: Coord, x, y| {
-            origin
-                .add(Grid::GRID_SIZE.emul(x as i32, y as i32))
-                .add(Grid::GRID_SIZE.div(2))
+impl Geometry {
+    // the order in which the calibration asks for the slots of the crafting table
+    pub const CALIBRATION: [&str; 7] = [
+        "the top-left slot of the crafting grid",
+        "the bottom-right slot of the crafting grid",
+        "the result slot",
+        "the top-left slot of the inventory",
+        "the bottom-right slot of the inventory",
+        "the leftmost slot of the hotbar",
+        "the rightmost slot of the hotbar",
+    ];
+
+    pub fn new(layout: &Layout, container: Container) -> Self {
+        let layout = layout.container(container.size());
+
+        Self {
+            slot: Coord(18, 18).mul(layout.scale()),
+            regions: container
+                .regions()
+                .into_iter()
+                .map(|region| Region {
+                    origin: layout.screen(region.origin),
+                    ..region
+                })
+                .collect(),
+        }
+    }
+
+    // of the crafting table, from the centres of the slots in the order of `CALIBRATION`
+    pub fn calibrate(points: &[Coord]) -> Result<Self> {
+        let &[
+            table_first,
+            table_last,
+            craft,
+            inv_first,
+            inv_last,
+            hotbar_first,
+            hotbar_last,
+        ] = points
+        else {
+            bail!("{} slots are required", Self::CALIBRATION.len());
+        };
+
+        // the inventory spans the most slots and gives the most precise size
+        let slot = span(inv_first, inv_last, Coord(8, 2));
+        let table = span(table_first, table_last, Coord(2, 2));
+        let hotbar = span(hotbar_first, hotbar_last, Coord(8, 1));
+
+        if slot.0 <= 0 || slot.1 <= 0 {
+            bail!("the inventory was clicked in the wrong order");
+        }
+
+        let is_close = |a: i32, b: i32| (a - b).abs() * 4 <= slot.0.min(slot.1);
+        if !is_close(table.0, slot.0) || !is_close(table.1, slot.1) || !is_close(hotbar.0, slot.0) {
+            bail!("the slots of the crafting grid, the inventory and the hotbar differ in size");
+        }
+
+        let region = |kind, centre: Coord, columns, rows| Region {
+            kind,
+            first: 0,
+            origin: centre.sub(slot.div(2)),
+            columns,
+            rows,
         };
 
-        match value {
-            Grid::Table(x, y) => f(Grid::ORIGIN_TABLE, *x, *y),
-            Grid::Craft => f(Grid::ORIGIN_CRAFT, 0, 0),
-            Grid::Inv(x, y) => f(Grid::ORIGIN_INV, *x, *y),
-            Grid::Hotbar(x) => f(Grid::ORIGIN_HOTBAR, *x, 0),
+        Ok(Self {
+            slot,
+            regions: Box::new([
+                region(Kind::Table, table_first, 3, 3),
+                region(Kind::Craft, craft, 1, 1),
+                region(Kind::Inv, inv_first, 9, 3),
+                region(Kind::Hotbar, hotbar_first, 9, 1),
+            ]),
+        })
+    }
+
+    pub fn offset(&self, origin: Coord) -> Self {
+        Self {
+            slot: self.slot,
+            regions: self
+                .regions
+                .iter()
+                .map(|region| Region {
+                    origin: region.origin.add(origin),
+                    ..*region
+                })
+                .collect(),
+        }
+    }
+
+    // every slot, region by region in the order of reading
+    pub fn slots(&self) -> impl Iterator<Item = Grid> {
+        self.regions.iter().flat_map(|region| {
+            (0..region.rows).flat_map(move |y| {
+                (0..region.columns).map(move |x| Grid::new(region.kind, region.first + x, y))
+            })
+        })
+    }
+
+    // the bounding box of the inventory and the hotbar, as (origin, size)
+    pub fn player(&self) -> Option<(Coord, Coord)> {
+        let find = |kind| self.regions.iter().find(|region| region.kind == kind);
+        let inv = find(Kind::Inv)?;
+        let hotbar = find(Kind::Hotbar)?;
+        let end = hotbar
+            .origin
+            .add(self.slot.emul(hotbar.columns.into(), hotbar.rows.into()));
+
+        Some((inv.origin, end.sub(inv.origin)))
+    }
+}
+
+// the size of a slot from the centres of the first and the last one, `slots` apart, rounded
+const fn span(first: Coord, last: Coord, slots: Coord) -> Coord {
+    let Coord(x, y) = last.sub(first);
+    Coord(
+        (2 * x + slots.0) / (2 * slots.0),
+        (2 * y + slots.1) / (2 * slots.1),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots_round_trip_in_every_container() {
+        for (size, gui_scale) in [((1920, 1080), 3), ((1366, 768), 0), ((854, 480), 1)] {
+            for container in Container::ALL {
+                let geometry = Geometry::new(&Layout::new(size, gui_scale), *container);
+
+                for slot in geometry.slots() {
+                    let coord = slot.coord(&geometry).unwrap();
+                    assert_eq!(Grid::at(coord, &geometry), Some(slot));
+                }
+            }
         }
     }
+
+    #[test]
+    fn matches_large_gui_at_full_hd() {
+        let geometry = Geometry::new(&Layout::new((1920, 1080), 3), Container::CraftingTable);
+
+        assert_eq!(Grid::Table(0, 0).coord(&geometry), Some(Coord(810, 366)));
+        assert_eq!(Grid::at(Coord(1065, 393), &geometry), Some(Grid::Craft));
+        assert_eq!(Grid::at(Coord(716, 540), &geometry), None);
+        assert_eq!(Grid::Fuel.coord(&geometry), None);
+        assert_eq!(geometry.player(), Some((Coord(717, 540), Coord(486, 228))));
+    }
+
+    #[test]
+    fn containers_keep_their_own_slots() {
+        let layout = Layout::new((1920, 1080), 3);
+        let anvil = Geometry::new(&layout, Container::Anvil);
+        let chest = Geometry::new(&layout, Container::LargeChest);
+
+        assert_eq!(anvil.slots().count(), 3 + 36);
+        assert_eq!(
+            Geometry::new(&layout, Container::Chest).slots().count(),
+            27 + 36
+        );
+        assert_eq!(chest.slots().count(), 54 + 36);
+
+        // 55 units further down in a container which starts 28 units higher, at scale 3
+        let inv = |geometry| Grid::Inv(0, 0).coord(geometry).unwrap();
+        assert_eq!(inv(&chest).sub(inv(&anvil)), Coord(0, 81));
+        assert_eq!(
+            Grid::at(Grid::Input(1).coord(&anvil).unwrap(), &anvil),
+            Some(Grid::Input(1))
+        );
+    }
+
+    #[test]
+    fn tells_empty_slots_from_items() {
+        let empty = RgbImage::from_pixel(24, 24, image::Rgb([139, 139, 139]));
+        assert!(is_empty(&empty));
+
+        let mut stone = empty.clone();
+        for (x, y, pixel) in stone.enumerate_pixels_mut() {
+            if (x + y) % 3 != 0 {
+                *pixel = image::Rgb([104, 104, 104]);
+            }
+        }
+        assert!(!is_empty(&stone));
+
+        let geometry = Geometry::new(&Layout::new((1920, 1080), 3), Container::CraftingTable);
+        assert_eq!(
+            Grid::Table(0, 0).inner(&geometry),
+            Some((Coord(797, 353), Coord(27, 27)))
+        );
+    }
+
+    #[test]
+    fn places_slots_on_the_pictures() {
+        let thumbnail = Coord(486, 228);
+        let at = |grid: Grid, picture| grid.picture(Container::CraftingTable, picture, thumbnail);
+
+        assert_eq!(at(Grid::Inv(0, 0), Picture::Thumbnail), Some(Coord(27, 27)));
+        assert_eq!(
+            at(Grid::Hotbar(8), Picture::Thumbnail),
+            Some(Coord(459, 201))
+        );
+        assert_eq!(at(Grid::Table(0, 0), Picture::Thumbnail), None);
+        assert_eq!(
+            Grid::Craft.picture(Container::CraftingTable, Picture::Item, Coord(78, 78)),
+            Some(Coord(39, 39))
+        );
+    }
+
+    #[test]
+    fn calibration_recovers_the_layout() -> Result<()> {
+        let expected = Geometry::new(&Layout::new((1366, 768), 3), Container::CraftingTable);
+        let centre = |grid: &Grid| grid.coord(&expected).unwrap();
+
+        // off by a few pixels, as clicks by hand would be
+        let points = [
+            centre(&Grid::Table(0, 0)).add(Coord(3, -2)),
+            centre(&Grid::Table(2, 2)).add(Coord(-1, 4)),
+            centre(&Grid::Craft),
+            centre(&Grid::Inv(0, 0)),
+            centre(&Grid::Inv(8, 2)).add(Coord(2, 1)),
+            centre(&Grid::Hotbar(0)),
+            centre(&Grid::Hotbar(8)).add(Coord(-5, 0)),
+        ];
+
+        let geometry = Geometry::calibrate(&points)?;
+        let Coord(dx, dy) = geometry.slot.sub(expected.slot);
+        assert!(dx.abs() <= 1 && dy.abs() <= 1);
+
+        for slot in expected.slots() {
+            assert_eq!(Grid::at(centre(&slot), &geometry).as_ref(), Some(&slot));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn calibration_rejects_misplaced_clicks() {
+        let expected = Geometry::new(&Layout::new((1920, 1080), 3), Container::CraftingTable);
+        let centre = |grid: &Grid| grid.coord(&expected).unwrap();
+
+        let mut points = [
+            centre(&Grid::Table(0, 0)),
+            centre(&Grid::Table(2, 2)),
+            centre(&Grid::Craft),
+            centre(&Grid::Inv(0, 0)),
+            centre(&Grid::Inv(8, 2)),
+            centre(&Grid::Hotbar(0)),
+            centre(&Grid::Hotbar(8)),
+        ];
+        assert_eq!(Geometry::calibrate(&points).ok().as_ref(), Some(&expected));
+        assert!(Geometry::calibrate(&points[..6]).is_err());
+
+        points.swap(3, 4);
+        assert!(Geometry::calibrate(&points).is_err());
+
+        points.swap(3, 4);
+        points[1] = centre(&Grid::Table(1, 1));
+        assert!(Geometry::calibrate(&points).is_err());
+    }
 }
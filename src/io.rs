@@ -1,151 +1,522 @@
-use crate::grid::Grid;
+use crate::coord::Coord;
+use crate::grid::{Container, Geometry};
+use crate::layout::Layout;
 use crate::map_err_anyhow::MapErrAnyhow;
+use crate::playlist::Playlist;
+use crate::recipe::Recipe;
 use anyhow::{Result, anyhow, bail};
+use image::RgbImage;
 use std::fs::File;
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
-use windows::Win32::UI::Input::KeyboardAndMouse::{
-    self as kam, INPUT, INPUT_0, KEYBDINPUT, MOUSEINPUT, VIRTUAL_KEY,
-};
-use windows::Win32::UI::WindowsAndMessaging as wam;
 
+#[cfg(target_os = "linux")]
+mod inotify;
+#[cfg(test)]
+pub mod mock;
+#[cfg(target_os = "linux")]
+mod uinput;
+#[cfg(windows)]
+mod windows;
+#[cfg(target_os = "linux")]
+mod x11;
+
+#[cfg(target_os = "linux")]
+pub use inotify::Inotify;
+#[cfg(target_os = "linux")]
+pub use uinput::Uinput;
+#[cfg(windows)]
+pub use windows::{Win32, message_box};
+#[cfg(target_os = "linux")]
+pub use x11::X11;
+
+// how the thumbnails are shown in the window, whatever size they were cropped at
 pub const INV_WIDTH: u32 = 486;
 pub const INV_HEIGHT: u32 = 228;
 pub const ITEM_WIDTH: u32 = 78;
 pub const ITEM_HEIGHT: u32 = 78;
 pub const FILENAME_THUMBNAIL: &str = "thumbnail.png";
 pub const FILENAME_ITEM: &str = "item.png";
+// the inventory with the hotbar when recording stopped, if it could be grabbed
+pub const FILENAME_AFTER: &str = "after.png";
 pub const FILENAME_CLICKS: &str = "clicks.json";
+pub const FILENAME_BACKUP: &str = "clicks.json.bak";
+pub const EXTENSION_PLAYLIST: &str = ".playlist.json";
+// next to the recipes, rewritten by each dry run
+pub const FILENAME_DRY_RUN: &str = "dry-run.log";
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum MouseButton {
     Left,
     Right,
+    Middle,
 }
 
-pub fn is_down(vkey: VIRTUAL_KEY) -> bool {
-    unsafe { kam::GetAsyncKeyState(vkey.0 as i32) }.cast_unsigned() >> 15 != 0
+// platform-neutral keys, with the mouse buttons so that they are polled together with the
+// keyboard (e.g. `LControl` + `MButton`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VirtualKey {
+    LButton,
+    RButton,
+    MButton,
+    XButton1,
+    XButton2,
+    Backspace,
+    Tab,
+    Return,
+    Escape,
+    Space,
+    Grave,
+    LShift,
+    RShift,
+    LControl,
+    RControl,
+    LAlt,
+    RAlt,
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Num0,
+    Num1,
+    Num2,
+    Num3,
+    Num4,
+    Num5,
+    Num6,
+    Num7,
+    Num8,
+    Num9,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
 }
 
-pub fn get_cursor() -> Result<(i32, i32)> {
-    let mut point = windows::Win32::Foundation::POINT::default();
+impl VirtualKey {
+    pub const ALL: &[Self] = &[
+        Self::LButton,
+        Self::RButton,
+        Self::MButton,
+        Self::XButton1,
+        Self::XButton2,
+        Self::Backspace,
+        Self::Tab,
+        Self::Return,
+        Self::Escape,
+        Self::Space,
+        Self::Grave,
+        Self::LShift,
+        Self::RShift,
+        Self::LControl,
+        Self::RControl,
+        Self::LAlt,
+        Self::RAlt,
+        Self::A,
+        Self::B,
+        Self::C,
+        Self::D,
+        Self::E,
+        Self::F,
+        Self::G,
+        Self::H,
+        Self::I,
+        Self::J,
+        Self::K,
+        Self::L,
+        Self::M,
+        Self::N,
+        Self::O,
+        Self::P,
+        Self::Q,
+        Self::R,
+        Self::S,
+        Self::T,
+        Self::U,
+        Self::V,
+        Self::W,
+        Self::X,
+        Self::Y,
+        Self::Z,
+        Self::Num0,
+        Self::Num1,
+        Self::Num2,
+        Self::Num3,
+        Self::Num4,
+        Self::Num5,
+        Self::Num6,
+        Self::Num7,
+        Self::Num8,
+        Self::Num9,
+        Self::F1,
+        Self::F2,
+        Self::F3,
+        Self::F4,
+        Self::F5,
+        Self::F6,
+        Self::F7,
+        Self::F8,
+        Self::F9,
+        Self::F10,
+        Self::F11,
+        Self::F12,
+    ];
+
+    // the number keys of the hotbar slots in the game
+    pub const HOTBAR: [Self; 9] = [
+        Self::Num1,
+        Self::Num2,
+        Self::Num3,
+        Self::Num4,
+        Self::Num5,
+        Self::Num6,
+        Self::Num7,
+        Self::Num8,
+        Self::Num9,
+    ];
+
+    pub const fn is_button(&self) -> bool {
+        matches!(
+            self,
+            Self::LButton | Self::RButton | Self::MButton | Self::XButton1 | Self::XButton2
+        )
+    }
 
-    unsafe {
-        wam::GetCursorPos(&mut point)?;
+    // the name used in the config file
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::LButton => "LButton",
+            Self::RButton => "RButton",
+            Self::MButton => "MButton",
+            Self::XButton1 => "XButton1",
+            Self::XButton2 => "XButton2",
+            Self::Backspace => "Backspace",
+            Self::Tab => "Tab",
+            Self::Return => "Return",
+            Self::Escape => "Escape",
+            Self::Space => "Space",
+            Self::Grave => "Grave",
+            Self::LShift => "LShift",
+            Self::RShift => "RShift",
+            Self::LControl => "LCtrl",
+            Self::RControl => "RCtrl",
+            Self::LAlt => "LAlt",
+            Self::RAlt => "RAlt",
+            Self::A => "A",
+            Self::B => "B",
+            Self::C => "C",
+            Self::D => "D",
+            Self::E => "E",
+            Self::F => "F",
+            Self::G => "G",
+            Self::H => "H",
+            Self::I => "I",
+            Self::J => "J",
+            Self::K => "K",
+            Self::L => "L",
+            Self::M => "M",
+            Self::N => "N",
+            Self::O => "O",
+            Self::P => "P",
+            Self::Q => "Q",
+            Self::R => "R",
+            Self::S => "S",
+            Self::T => "T",
+            Self::U => "U",
+            Self::V => "V",
+            Self::W => "W",
+            Self::X => "X",
+            Self::Y => "Y",
+            Self::Z => "Z",
+            Self::Num0 => "0",
+            Self::Num1 => "1",
+            Self::Num2 => "2",
+            Self::Num3 => "3",
+            Self::Num4 => "4",
+            Self::Num5 => "5",
+            Self::Num6 => "6",
+            Self::Num7 => "7",
+            Self::Num8 => "8",
+            Self::Num9 => "9",
+            Self::F1 => "F1",
+            Self::F2 => "F2",
+            Self::F3 => "F3",
+            Self::F4 => "F4",
+            Self::F5 => "F5",
+            Self::F6 => "F6",
+            Self::F7 => "F7",
+            Self::F8 => "F8",
+            Self::F9 => "F9",
+            Self::F10 => "F10",
+            Self::F11 => "F11",
+            Self::F12 => "F12",
+        }
     }
 
-    Ok((point.x, point.y))
+    // case-insensitive, and a modifier without a side means the left one
+    pub fn from_name(name: &str) -> Option<Self> {
+        let alias = match name.to_ascii_lowercase().as_str() {
+            "ctrl" | "lcontrol" => Some(Self::LControl),
+            "rcontrol" => Some(Self::RControl),
+            "alt" => Some(Self::LAlt),
+            "shift" => Some(Self::LShift),
+            "enter" => Some(Self::Return),
+            "esc" => Some(Self::Escape),
+            "`" | "backtick" => Some(Self::Grave),
+            _ => None,
+        };
+
+        alias.or_else(|| {
+            Self::ALL
+                .iter()
+                .copied()
+                .find(|key| key.name().eq_ignore_ascii_case(name))
+        })
+    }
 }
 
-pub fn set_cursor(x: i32, y: i32) -> Result<()> {
-    unsafe { wam::SetCursorPos(x, y) }.map_err_anyhow()
+impl From<MouseButton> for VirtualKey {
+    fn from(value: MouseButton) -> Self {
+        match value {
+            MouseButton::Left => Self::LButton,
+            MouseButton::Right => Self::RButton,
+            MouseButton::Middle => Self::MButton,
+        }
+    }
 }
 
-pub fn send_key_down(vkey: VIRTUAL_KEY) {
-    let input = INPUT {
-        r#type: kam::INPUT_KEYBOARD,
-        Anonymous: INPUT_0 {
-            ki: KEYBDINPUT {
-                wVk: vkey,
-                ..Default::default()
-            },
-        },
-    };
-
-    send_inputs(&[input]);
+// the client area of a window, in screen coordinates
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClientRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
 }
 
-pub fn send_key_up(vkey: VIRTUAL_KEY) {
-    let input = INPUT {
-        r#type: kam::INPUT_KEYBOARD,
-        Anonymous: INPUT_0 {
-            ki: KEYBDINPUT {
-                wVk: vkey,
-                dwFlags: kam::KEYEVENTF_KEYUP,
-                ..Default::default()
-            },
-        },
-    };
+// everything the state machine needs from the OS: the global key state, the cursor and synthetic
+// inputs, none of which require the window of this program to be focused
+pub trait InputBackend {
+    fn is_down(&self, key: VirtualKey) -> bool;
+
+    fn get_cursor(&self) -> Result<(i32, i32)>;
+
+    fn set_cursor(&self, x: i32, y: i32) -> Result<()>;
+
+    fn send_key_down(&self, key: VirtualKey) -> Result<()>;
+
+    fn send_key_up(&self, key: VirtualKey) -> Result<()>;
+
+    fn send_mouse_down(&self, button: MouseButton) -> Result<()>;
 
-    send_inputs(&[input]);
+    fn send_mouse_up(&self, button: MouseButton) -> Result<()>;
+
+    fn send_mouse(&self, button: MouseButton) -> Result<()> {
+        self.send_mouse_down(button)?;
+        self.send_mouse_up(button)
+    }
+
+    fn message_box(&self, msg: &str, title: &str) -> Result<()>;
+
+    // catches up with what happened since the last step, for backends which follow the devices
+    fn update(&self) -> Result<()> {
+        Ok(())
+    }
+
+    // the first visible window whose title or process name contains `name`, ignoring case
+    fn find_window(&self, name: &str) -> Result<Option<ClientRect>>;
+
+    // what is shown in a rectangle of the screen, or `None` if the screen cannot be read
+    fn capture(&self, _x: i32, _y: i32, _width: u32, _height: u32) -> Result<Option<RgbImage>> {
+        Ok(None)
+    }
 }
 
-pub fn send_mouse_down(button: MouseButton) {
-    let flag_down = match button {
-        MouseButton::Left => kam::MOUSEEVENTF_LEFTDOWN,
-        MouseButton::Right => kam::MOUSEEVENTF_RIGHTDOWN,
-    };
+impl<B: InputBackend + ?Sized> InputBackend for std::rc::Rc<B> {
+    fn is_down(&self, key: VirtualKey) -> bool {
+        self.as_ref().is_down(key)
+    }
 
-    let input = INPUT {
-        r#type: kam::INPUT_MOUSE,
-        Anonymous: INPUT_0 {
-            mi: MOUSEINPUT {
-                dwFlags: flag_down,
-                ..Default::default()
-            },
-        },
-    };
+    fn get_cursor(&self) -> Result<(i32, i32)> {
+        self.as_ref().get_cursor()
+    }
+
+    fn set_cursor(&self, x: i32, y: i32) -> Result<()> {
+        self.as_ref().set_cursor(x, y)
+    }
 
-    send_inputs(&[input]);
+    fn send_key_down(&self, key: VirtualKey) -> Result<()> {
+        self.as_ref().send_key_down(key)
+    }
+
+    fn send_key_up(&self, key: VirtualKey) -> Result<()> {
+        self.as_ref().send_key_up(key)
+    }
+
+    fn send_mouse_down(&self, button: MouseButton) -> Result<()> {
+        self.as_ref().send_mouse_down(button)
+    }
+
+    fn send_mouse_up(&self, button: MouseButton) -> Result<()> {
+        self.as_ref().send_mouse_up(button)
+    }
+
+    fn send_mouse(&self, button: MouseButton) -> Result<()> {
+        self.as_ref().send_mouse(button)
+    }
+
+    fn message_box(&self, msg: &str, title: &str) -> Result<()> {
+        self.as_ref().message_box(msg, title)
+    }
+
+    fn update(&self) -> Result<()> {
+        self.as_ref().update()
+    }
+
+    fn capture(&self, x: i32, y: i32, width: u32, height: u32) -> Result<Option<RgbImage>> {
+        self.as_ref().capture(x, y, width, height)
+    }
+
+    fn find_window(&self, name: &str) -> Result<Option<ClientRect>> {
+        self.as_ref().find_window(name)
+    }
 }
 
-pub fn send_mouse_up(button: MouseButton) {
-    let flag_up = match button {
-        MouseButton::Left => kam::MOUSEEVENTF_LEFTUP,
-        MouseButton::Right => kam::MOUSEEVENTF_RIGHTUP,
-    };
+// the files written into a directory, as they are closed
+pub trait Watcher {
+    // since the last call
+    fn poll(&self) -> Result<Vec<PathBuf>>;
+}
 
-    let input = INPUT {
-        r#type: kam::INPUT_MOUSE,
-        Anonymous: INPUT_0 {
-            mi: MOUSEINPUT {
-                dwFlags: flag_up,
-                ..Default::default()
-            },
-        },
-    };
+#[cfg(target_os = "linux")]
+pub fn watcher(dir: impl AsRef<Path>) -> Result<Option<Box<dyn Watcher>>> {
+    Ok(Some(Box::new(Inotify::new(dir)?)))
+}
 
-    send_inputs(&[input]);
+// the latest screenshots are taken instead
+#[cfg(not(target_os = "linux"))]
+pub fn watcher(_dir: impl AsRef<Path>) -> Result<Option<Box<dyn Watcher>>> {
+    Ok(None)
 }
 
-pub fn send_mouse(button: MouseButton) {
-    let (flag_down, flag_up) = match button {
-        MouseButton::Left => (kam::MOUSEEVENTF_LEFTDOWN, kam::MOUSEEVENTF_LEFTUP),
-        MouseButton::Right => (kam::MOUSEEVENTF_RIGHTDOWN, kam::MOUSEEVENTF_RIGHTUP),
-    };
+#[cfg(windows)]
+pub fn default_backend(_desktop: (i32, i32)) -> Result<Box<dyn InputBackend>> {
+    Ok(Box::new(Win32))
+}
 
-    let down = INPUT {
-        r#type: kam::INPUT_MOUSE,
-        Anonymous: INPUT_0 {
-            mi: MOUSEINPUT {
-                dwFlags: flag_down,
-                ..Default::default()
-            },
+// X11 is preferred since it knows where the cursor is. uinput also works without an X server
+// (e.g. on Wayland), given the permissions.
+#[cfg(target_os = "linux")]
+pub fn default_backend(desktop: (i32, i32)) -> Result<Box<dyn InputBackend>> {
+    match X11::new() {
+        Ok(x11) => Ok(Box::new(x11)),
+        Err(e_x11) => match Uinput::new(desktop) {
+            Ok(uinput) => Ok(Box::new(uinput)),
+            Err(e_uinput) => bail!("no input backend available (X11: {e_x11}, uinput: {e_uinput})"),
         },
-    };
+    }
+}
 
-    let up = INPUT {
-        r#type: kam::INPUT_MOUSE,
-        Anonymous: INPUT_0 {
-            mi: MOUSEINPUT {
-                dwFlags: flag_up,
-                ..Default::default()
-            },
-        },
-    };
+#[cfg(not(windows))]
+pub fn message_box(msg: impl AsRef<str>, title: impl AsRef<str>) -> Result<()> {
+    use sdl2::messagebox::{MessageBoxFlag, show_simple_message_box};
+
+    show_simple_message_box(
+        MessageBoxFlag::INFORMATION,
+        title.as_ref(),
+        msg.as_ref(),
+        None,
+    )
+    .map_err_anyhow()
+}
 
-    send_inputs(&[down, up]);
+// grabbed from the screen while recording, where the backend can read it
+#[derive(Default)]
+pub struct Frames {
+    // at the start, with the ingredients
+    pub inventory: Option<RgbImage>,
+    // at the end, with the result
+    pub item: Option<RgbImage>,
+    // at the end, with what is left of the ingredients
+    pub after: Option<RgbImage>,
+    // taken while recording, in order, if the screenshot directory is watched
+    pub screenshots: Option<Vec<PathBuf>>,
 }
 
-pub fn save_clicks<P1, P2>(screenshots: P1, recipes: P2, clicks: &[Grid], name: &str) -> Result<()>
+// the pictures come from the screenshots taken while recording, or else the grabbed frames. if the
+// screenshot directory is not watched, the two latest screenshots are guessed instead.
+pub fn save_recipe<P1, P2>(
+    screenshots: P1,
+    recipes: P2,
+    recipe: &Recipe,
+    name: &str,
+    gui_scale: u8,
+    frames: &Frames,
+) -> Result<()>
 where
     P1: AsRef<Path>,
     P2: AsRef<Path>,
 {
+    enum Source<'a> {
+        Grabbed(&'a RgbImage, &'a RgbImage),
+        Screenshots(PathBuf, PathBuf),
+    }
+
     // special message for an empty name (even without this, create_dir would return an error)
     if name.is_empty() {
         bail!("cannot save with empty name");
     }
 
+    let source = match (frames, frames.screenshots.as_deref()) {
+        (_, Some([inventory, item])) => Source::Screenshots(inventory.clone(), item.clone()),
+        (
+            Frames {
+                inventory: Some(inventory),
+                item: Some(item),
+                ..
+            },
+            None | Some([]),
+        ) => Source::Grabbed(inventory, item),
+        (_, Some(paths)) => bail!(
+            "two screenshots are required (F2 in the game), but {} was taken while recording",
+            paths.len()
+        ),
+        (_, None) => {
+            let (inventory, item) = get_latest_pngs(screenshots)?;
+            Source::Screenshots(inventory, item)
+        }
+    };
+
     let dir = recipes.as_ref().join(name);
 
     std::fs::create_dir(&dir)?;
@@ -153,13 +524,26 @@ where
     let result = File::create_new(dir.join(FILENAME_CLICKS))
         .map_err_anyhow()
         .and_then(|json| {
-            crop_latest_pngs(
-                screenshots,
-                dir.join(FILENAME_THUMBNAIL),
-                dir.join(FILENAME_ITEM),
-            )?;
-
-            serde_json::to_writer(json, clicks).map_err_anyhow()
+            match source {
+                Source::Grabbed(inventory, item) => {
+                    inventory.save(dir.join(FILENAME_THUMBNAIL))?;
+                    item.save(dir.join(FILENAME_ITEM))?;
+                }
+                Source::Screenshots(inventory, item) => crop_pngs(
+                    inventory,
+                    item,
+                    dir.join(FILENAME_THUMBNAIL),
+                    dir.join(FILENAME_ITEM),
+                    recipe.container,
+                    gui_scale,
+                )?,
+            }
+
+            if let Some(after) = &frames.after {
+                after.save(dir.join(FILENAME_AFTER))?;
+            }
+
+            recipe.to_writer(json)
         });
 
     if result.is_err() {
@@ -169,15 +553,99 @@ where
     result
 }
 
-pub fn load_clicks(path: impl AsRef<Path>) -> Result<Box<[Grid]>> {
-    let file = File::open(path)?;
-    serde_json::from_reader(file).map_err_anyhow()
+// recipes of older versions are upgraded in memory only
+pub fn load_recipe(path: impl AsRef<Path>) -> Result<Recipe> {
+    let path = path.as_ref();
+
+    Recipe::from_reader(File::open(path)?, path.metadata()?.modified()?)
+        .map_err(|e| anyhow!("{}: {e}", path.display()))
+}
+
+// over `clicks.json` in `dir`, which is never left half-written
+pub fn update_recipe(dir: impl AsRef<Path>, recipe: &Recipe) -> Result<()> {
+    let dir = dir.as_ref();
+    let temporary = dir.join(format!("{FILENAME_CLICKS}.tmp"));
+
+    recipe.to_writer(File::create(&temporary)?)?;
+    std::fs::rename(temporary, dir.join(FILENAME_CLICKS))?;
+
+    Ok(())
+}
+
+// by name, e.g. `tools` for `tools.playlist.json` next to the recipes
+pub fn playlists(dir: impl AsRef<Path>) -> Result<Box<[(String, Playlist)]>> {
+    let mut playlists = Vec::new();
+
+    for entry in std::fs::read_dir(dir)?.filter_map(Result::ok) {
+        let path = entry.path();
+        let Some(name) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(EXTENSION_PLAYLIST))
+        else {
+            continue;
+        };
+
+        let playlist = Playlist::from_reader(File::open(&path)?)
+            .map_err(|e| anyhow!("{}: {e}", path.display()))?;
+        playlists.push((name.to_string(), playlist));
+    }
+
+    playlists.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+    Ok(playlists.into())
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Migration {
+    pub upgraded: usize,
+    pub current: usize,
+    // left as they are, since a backup is already next to them
+    pub skipped: Vec<PathBuf>,
+}
+
+// rewrites the recipes of older versions in place, each next to a backup of the original
+pub fn migrate(recipes: impl AsRef<Path>) -> Result<Migration> {
+    let mut migration = Migration::default();
+
+    for dir in self::recipes(recipes)? {
+        let path = dir.join(FILENAME_CLICKS);
+
+        if !path.is_file() {
+            continue;
+        }
+
+        let value = serde_json::from_reader(File::open(&path)?)?;
+        let (recipe, upgraded) = Recipe::upgrade(value, path.metadata()?.modified()?)
+            .map_err(|e| anyhow!("{}: {e}", path.display()))?;
+
+        if !upgraded {
+            migration.current += 1;
+            continue;
+        }
+
+        let backup = dir.join(FILENAME_BACKUP);
+
+        if backup.exists() {
+            migration.skipped.push(dir);
+            continue;
+        }
+
+        std::fs::copy(&path, backup)?;
+        update_recipe(&dir, &recipe)?;
+
+        migration.upgraded += 1;
+    }
+
+    Ok(migration)
 }
 
 pub fn recipes(path: impl AsRef<Path>) -> Result<Box<[PathBuf]>> {
     let mut boxed: Box<[PathBuf]> = std::fs::read_dir(path)?
         .filter_map(Result::ok)
         .map(|entry| entry.path())
+        // the playlists are files next to them
+        .filter(|path| path.is_dir())
         .collect();
 
     boxed.sort_unstable();
@@ -185,50 +653,47 @@ pub fn recipes(path: impl AsRef<Path>) -> Result<Box<[PathBuf]>> {
     Ok(boxed)
 }
 
-pub fn message_box<V1, V2>(msg: V1, title: V2) -> Result<()>
-where
-    V1: Into<Vec<u8>>,
-    V2: Into<Vec<u8>>,
-{
-    use std::ffi::CString;
-    use windows::core::PCSTR;
-
-    let msg = CString::new(msg)?;
-    let title = CString::new(title)?;
-
-    unsafe {
-        wam::MessageBoxA(
-            None,
-            PCSTR::from_raw(msg.as_bytes().as_ptr()),
-            PCSTR::from_raw(title.as_bytes().as_ptr()),
-            wam::MB_ICONINFORMATION,
-        );
-    }
-
-    Ok(())
-}
-
-fn crop_latest_pngs<P1, P2, P3>(search_in: P1, dst_inv: P2, dst_item: P3) -> Result<()>
-where
-    P1: AsRef<Path>,
-    P2: AsRef<Path>,
-    P3: AsRef<Path>,
-{
-    use image::ImageReader;
-
-    let (path_inv, path_item) = get_latest_pngs(search_in)?;
+// the layout is derived from the size of each screenshot, which is that of the game
+fn crop_pngs(
+    path_inv: impl AsRef<Path>,
+    path_item: impl AsRef<Path>,
+    dst_inv: impl AsRef<Path>,
+    dst_item: impl AsRef<Path>,
+    container: Container,
+    gui_scale: u8,
+) -> Result<()> {
+    use image::{DynamicImage, ImageReader};
 
     let mut image_inv = ImageReader::open(path_inv)?.decode()?;
     let mut image_item = ImageReader::open(path_item)?.decode()?;
 
-    image_inv
-        .crop(717, 540, INV_WIDTH, INV_HEIGHT)
-        .save(dst_inv)?;
+    let layout = |image: &DynamicImage| {
+        Layout::new((image.width() as i32, image.height() as i32), gui_scale)
+    };
+    let crop = |image: &mut DynamicImage, Coord(x, y): Coord, Coord(width, height): Coord| {
+        image.crop(x as u32, y as u32, width as u32, height as u32)
+    };
 
-    image_item
-        .crop(1053, 381, ITEM_WIDTH, ITEM_HEIGHT)
-        .save(dst_item)
-        .map_err_anyhow()
+    // the inventory with the hotbar, which every container has
+    let (origin, size) = Geometry::new(&layout(&image_inv), container)
+        .player()
+        .ok_or_else(|| anyhow!("{container:?} has no inventory"))?;
+    crop(&mut image_inv, origin, size).save(dst_inv)?;
+
+    let layout = layout(&image_item).container(container.size());
+    let (origin, size) = container.item();
+    crop(
+        &mut image_item,
+        layout.screen(origin),
+        size.mul(layout.scale()),
+    )
+    .save(dst_item)
+    .map_err_anyhow()
+}
+
+fn is_png(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
 }
 
 // (second latest, most latest)
@@ -248,13 +713,7 @@ fn get_latest_pngs(path: impl AsRef<Path>) -> Result<(PathBuf, PathBuf)> {
                 })
             })
         })
-        .filter(|entry| {
-            entry
-                .path
-                .extension()
-                .map(|ext| ext.eq_ignore_ascii_case("png"))
-                .unwrap_or(false)
-        })
+        .filter(|entry| is_png(&entry.path))
         .collect();
 
     entries.sort_unstable_by_key(|entry| entry.modified);
@@ -264,8 +723,84 @@ fn get_latest_pngs(path: impl AsRef<Path>) -> Result<(PathBuf, PathBuf)> {
         .ok_or_else(|| anyhow!("two screenshots are required"))
 }
 
-fn send_inputs(inputs: &[INPUT]) {
-    unsafe {
-        kam::SendInput(inputs, size_of::<INPUT>() as i32);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{grid::Grid, recipe::Step};
+    use tempfile::TempDir;
+
+    #[test]
+    fn migrates_in_place_with_backups() -> Result<()> {
+        let recipes = TempDir::new()?;
+        let write = |name: &str, json: &str| -> Result<PathBuf> {
+            let dir = recipes.path().join(name);
+            std::fs::create_dir(&dir)?;
+            std::fs::write(dir.join(FILENAME_CLICKS), json)?;
+            Ok(dir)
+        };
+
+        let old = write("planks", r#"[{"Table":[0,0]},"Craft"]"#)?;
+        let new = write("sticks", "")?;
+        Recipe::new(Container::CraftingTable, Default::default(), Vec::new())
+            .to_writer(File::create(new.join(FILENAME_CLICKS))?)?;
+        // sorted before the others, which are upgraded all the same
+        let kept = write("boats", r#"["Craft"]"#)?;
+        std::fs::write(kept.join(FILENAME_BACKUP), "")?;
+
+        assert_eq!(
+            migrate(recipes.path())?,
+            Migration {
+                upgraded: 1,
+                current: 1,
+                skipped: vec![kept.clone()],
+            }
+        );
+        assert_eq!(
+            std::fs::read_to_string(kept.join(FILENAME_CLICKS))?,
+            r#"["Craft"]"#
+        );
+        assert_eq!(
+            std::fs::read_to_string(old.join(FILENAME_BACKUP))?,
+            r#"[{"Table":[0,0]},"Craft"]"#
+        );
+        assert!(!new.join(FILENAME_BACKUP).exists());
+
+        let recipe: Recipe = serde_json::from_reader(File::open(old.join(FILENAME_CLICKS))?)?;
+        assert_eq!(recipe.version, Recipe::VERSION);
+        assert_eq!(
+            recipe.steps().collect::<Vec<_>>(),
+            [&Step::Left(Grid::Table(0, 0)), &Step::Left(Grid::Craft)]
+        );
+
+        // nothing is left to do, and the backups stay as they are
+        assert_eq!(
+            migrate(recipes.path())?,
+            Migration {
+                upgraded: 0,
+                current: 2,
+                skipped: vec![kept],
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn lists_playlists_apart_from_recipes() -> Result<()> {
+        let recipes = TempDir::new()?;
+        std::fs::create_dir(recipes.path().join("planks"))?;
+        std::fs::write(
+            recipes.path().join("tools.playlist.json"),
+            r#"{"stages":[{"recipe":"planks","repeat":2}]}"#,
+        )?;
+
+        assert_eq!(self::recipes(recipes.path())?.len(), 1);
+
+        let playlists = playlists(recipes.path())?;
+        assert_eq!(playlists.len(), 1);
+        assert_eq!(playlists[0].0, "tools");
+        assert_eq!(playlists[0].1.stages[0].repeat, 2);
+
+        Ok(())
     }
 }
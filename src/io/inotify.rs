@@ -0,0 +1,49 @@
+use super::Watcher;
+use anyhow::Result;
+use nix::{
+    errno::Errno,
+    sys::inotify::{AddWatchFlags, InitFlags},
+};
+use std::path::{Path, PathBuf};
+
+// PNGs are reported once they are written or moved into the directory, which is not recursive
+pub struct Inotify {
+    inotify: nix::sys::inotify::Inotify,
+    dir: PathBuf,
+}
+
+impl Inotify {
+    pub fn new(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let inotify =
+            nix::sys::inotify::Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)?;
+        inotify.add_watch(
+            dir,
+            AddWatchFlags::IN_CLOSE_WRITE | AddWatchFlags::IN_MOVED_TO,
+        )?;
+
+        Ok(Self {
+            inotify,
+            dir: dir.to_path_buf(),
+        })
+    }
+}
+
+impl Watcher for Inotify {
+    fn poll(&self) -> Result<Vec<PathBuf>> {
+        let events = match self.inotify.read_events() {
+            Ok(events) => events,
+            Err(Errno::EAGAIN) => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let paths = events
+            .into_iter()
+            .filter_map(|event| event.name)
+            .map(|name| self.dir.join(name))
+            .filter(|path| super::is_png(path))
+            .collect();
+
+        Ok(paths)
+    }
+}
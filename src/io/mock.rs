@@ -0,0 +1,222 @@
+use super::{ClientRect, InputBackend, MouseButton, VirtualKey};
+use crate::clock::ManualClock;
+use anyhow::{Result, bail};
+use image::{RgbImage, imageops};
+use std::{
+    cell::{Cell, RefCell},
+    path::Path,
+    rc::Rc,
+    time::Duration,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Input {
+    Down(VirtualKey),
+    Up(VirtualKey),
+    Cursor(i32, i32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Output {
+    KeyDown(VirtualKey),
+    KeyUp(VirtualKey),
+    MouseDown(MouseButton),
+    MouseUp(MouseButton),
+    Cursor(i32, i32),
+    MessageBox(String),
+}
+
+// replays a timeline of user inputs against a manual clock and logs what would have been injected.
+// injected inputs are not reflected in `is_down`.
+pub struct Scripted {
+    clock: Rc<ManualClock>,
+    timeline: RefCell<Vec<(Duration, Input)>>,
+    cursor: Cell<(Duration, (i32, i32))>,
+    log: RefCell<Vec<(Duration, Output)>>,
+    window: RefCell<Option<(String, ClientRect)>>,
+    screen: RefCell<Option<RgbImage>>,
+}
+
+impl Scripted {
+    pub const HOLD: Duration = Duration::from_millis(20);
+
+    pub fn new(clock: Rc<ManualClock>) -> Self {
+        Self {
+            clock,
+            timeline: RefCell::default(),
+            cursor: Cell::new((Duration::ZERO, (0, 0))),
+            log: RefCell::default(),
+            window: RefCell::default(),
+            screen: RefCell::default(),
+        }
+    }
+
+    pub fn push(&self, at: Duration, input: Input) {
+        let mut timeline = self.timeline.borrow_mut();
+        let index = timeline.partition_point(|(time, _)| *time <= at);
+        timeline.insert(index, (at, input));
+    }
+
+    pub fn down(&self, at: Duration, key: VirtualKey) {
+        self.push(at, Input::Down(key));
+    }
+
+    pub fn up(&self, at: Duration, key: VirtualKey) {
+        self.push(at, Input::Up(key));
+    }
+
+    // presses every key at once and releases them after `HOLD`
+    pub fn tap(&self, at: Duration, keys: &[VirtualKey]) {
+        for key in keys {
+            self.down(at, *key);
+            self.up(at + Self::HOLD, *key);
+        }
+    }
+
+    pub fn move_to(&self, at: Duration, (x, y): (i32, i32)) {
+        self.push(at, Input::Cursor(x, y));
+    }
+
+    pub fn click_at(&self, at: Duration, position: (i32, i32)) {
+        self.move_to(at, position);
+        self.tap(at, &[VirtualKey::LButton]);
+    }
+
+    pub fn set_window(&self, title: &str, rect: ClientRect) {
+        *self.window.borrow_mut() = Some((title.to_string(), rect));
+    }
+
+    pub fn set_screen(&self, image: RgbImage) {
+        *self.screen.borrow_mut() = Some(image);
+    }
+
+    // what the screen shows from now on, as saved in a file
+    pub fn load_screen(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.set_screen(image::open(path)?.into_rgb8());
+        Ok(())
+    }
+
+    pub fn log(&self) -> Vec<(Duration, Output)> {
+        self.log.borrow().clone()
+    }
+
+    pub fn outputs(&self) -> Vec<Output> {
+        self.log
+            .borrow()
+            .iter()
+            .map(|(_, output)| output.clone())
+            .collect()
+    }
+
+    pub fn clear_log(&self) {
+        self.log.borrow_mut().clear();
+    }
+
+    fn record(&self, output: Output) {
+        self.log.borrow_mut().push((self.clock.elapsed(), output));
+    }
+
+    fn past(&self) -> Vec<(Duration, Input)> {
+        let now = self.clock.elapsed();
+
+        self.timeline
+            .borrow()
+            .iter()
+            .take_while(|(time, _)| *time <= now)
+            .copied()
+            .collect()
+    }
+}
+
+impl InputBackend for Scripted {
+    fn is_down(&self, key: VirtualKey) -> bool {
+        self.past()
+            .iter()
+            .rev()
+            .find_map(|(_, input)| match input {
+                Input::Down(k) if *k == key => Some(true),
+                Input::Up(k) if *k == key => Some(false),
+                _ => None,
+            })
+            .unwrap_or(false)
+    }
+
+    fn get_cursor(&self) -> Result<(i32, i32)> {
+        let scripted = self
+            .past()
+            .iter()
+            .rev()
+            .find_map(|(time, input)| match input {
+                Input::Cursor(x, y) => Some((*time, (*x, *y))),
+                _ => None,
+            });
+
+        let (_, position) = match scripted {
+            Some(scripted) if self.cursor.get().0 <= scripted.0 => scripted,
+            _ => self.cursor.get(),
+        };
+
+        Ok(position)
+    }
+
+    fn set_cursor(&self, x: i32, y: i32) -> Result<()> {
+        self.cursor.set((self.clock.elapsed(), (x, y)));
+        self.record(Output::Cursor(x, y));
+        Ok(())
+    }
+
+    fn send_key_down(&self, key: VirtualKey) -> Result<()> {
+        self.record(Output::KeyDown(key));
+        Ok(())
+    }
+
+    fn send_key_up(&self, key: VirtualKey) -> Result<()> {
+        self.record(Output::KeyUp(key));
+        Ok(())
+    }
+
+    fn send_mouse_down(&self, button: MouseButton) -> Result<()> {
+        self.record(Output::MouseDown(button));
+        Ok(())
+    }
+
+    fn send_mouse_up(&self, button: MouseButton) -> Result<()> {
+        self.record(Output::MouseUp(button));
+        Ok(())
+    }
+
+    fn message_box(&self, msg: &str, _title: &str) -> Result<()> {
+        self.record(Output::MessageBox(msg.to_string()));
+        Ok(())
+    }
+
+    fn find_window(&self, name: &str) -> Result<Option<ClientRect>> {
+        let window = self.window.borrow();
+        let rect = window
+            .as_ref()
+            .filter(|(title, _)| title.to_lowercase().contains(&name.to_lowercase()))
+            .map(|(_, rect)| *rect);
+
+        Ok(rect)
+    }
+
+    // like a real display, which refuses regions beyond its edges
+    fn capture(&self, x: i32, y: i32, width: u32, height: u32) -> Result<Option<RgbImage>> {
+        let screen = self.screen.borrow();
+        let Some(screen) = screen.as_ref() else {
+            return Ok(None);
+        };
+
+        if x < 0
+            || y < 0
+            || screen.width() < x as u32 + width
+            || screen.height() < y as u32 + height
+        {
+            bail!("{width}x{height} at ({x}, {y}) is not on the screen");
+        }
+
+        Ok(Some(
+            imageops::crop_imm(screen, x as u32, y as u32, width, height).to_image(),
+        ))
+    }
+}
@@ -0,0 +1,506 @@
+use super::{ClientRect, InputBackend, MouseButton, VirtualKey};
+use anyhow::Result;
+use evdev::{
+    AbsInfo, AbsoluteAxisCode, AttributeSet, EventSummary, EventType, InputEvent, KeyCode,
+    RelativeAxisCode, SynchronizationCode, UinputAbsSetup, raw_stream::RawDevice,
+    uinput::VirtualDevice,
+};
+use std::{cell::RefCell, fs::OpenOptions, io::ErrorKind, os::unix::fs::OpenOptionsExt};
+
+// Synthetic inputs go through two virtual devices (a keyboard, and an absolute pointer so that
+// the cursor can be warped), while key state and cursor movement are read from every
+// /dev/input/event* device which existed when the backend was created.
+pub struct Uinput {
+    keyboard: RefCell<VirtualDevice>,
+    pointer: RefCell<VirtualDevice>,
+    sources: RefCell<Box<[Source]>>,
+    tracker: RefCell<Tracker>,
+}
+
+struct Source<E = RawDevice> {
+    device: E,
+    abs_x: Option<(i32, i32)>,
+    abs_y: Option<(i32, i32)>,
+}
+
+// the events of a device since the last call, without blocking
+trait Events {
+    fn fetch(&mut self) -> std::io::Result<Vec<InputEvent>>;
+}
+
+// there is no global cursor at this level, so it is tracked by accumulating the events of every
+// pointing device. pointer acceleration of the compositor is not taken into account.
+struct Tracker {
+    desktop: (i32, i32),
+    cursor: (i32, i32),
+    // whether the real cursor may be elsewhere, at first and after the kernel dropped events, until
+    // it is warped to `cursor`
+    is_lost: bool,
+}
+
+impl Uinput {
+    const NAME_KEYBOARD: &str = "mctool keyboard";
+    const NAME_POINTER: &str = "mctool pointer";
+
+    pub fn new(desktop: (i32, i32)) -> Result<Self> {
+        let mut sources = Vec::new();
+
+        for entry in std::fs::read_dir("/dev/input")? {
+            let path = entry?.path();
+
+            if !path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("event"))
+            {
+                continue;
+            }
+
+            // read in every step, so that the buffer of the kernel does not overflow
+            let Ok(file) = OpenOptions::new()
+                .read(true)
+                .custom_flags(nix::libc::O_NONBLOCK)
+                .open(&path)
+            else {
+                continue;
+            };
+
+            sources.extend(Source::new(RawDevice::try_from(file)?)?);
+        }
+
+        let keyboard_keys: AttributeSet<KeyCode> = VirtualKey::ALL
+            .iter()
+            .filter(|key| !key.is_button())
+            .map(|key| key_code(*key))
+            .collect();
+
+        let pointer_keys: AttributeSet<KeyCode> = VirtualKey::ALL
+            .iter()
+            .filter(|key| key.is_button())
+            .map(|key| key_code(*key))
+            .collect();
+
+        let keyboard = VirtualDevice::builder()?
+            .name(Self::NAME_KEYBOARD)
+            .with_keys(&keyboard_keys)?
+            .build()?;
+
+        let pointer = VirtualDevice::builder()?
+            .name(Self::NAME_POINTER)
+            .with_keys(&pointer_keys)?
+            .with_absolute_axis(&UinputAbsSetup::new(
+                AbsoluteAxisCode::ABS_X,
+                AbsInfo::new(0, 0, desktop.0 - 1, 0, 0, 0),
+            ))?
+            .with_absolute_axis(&UinputAbsSetup::new(
+                AbsoluteAxisCode::ABS_Y,
+                AbsInfo::new(0, 0, desktop.1 - 1, 0, 0, 0),
+            ))?
+            .build()?;
+
+        Ok(Self {
+            keyboard: RefCell::new(keyboard),
+            pointer: RefCell::new(pointer),
+            sources: RefCell::new(sources.into()),
+            tracker: RefCell::new(Tracker::new(desktop)),
+        })
+    }
+
+    fn send_key(&self, key: VirtualKey, value: i32) -> Result<()> {
+        let device = if key.is_button() {
+            &self.pointer
+        } else {
+            &self.keyboard
+        };
+
+        let event = InputEvent::new(EventType::KEY.0, key_code(key).0, value);
+        device.borrow_mut().emit(&[event]).map_err(Into::into)
+    }
+}
+
+impl InputBackend for Uinput {
+    fn is_down(&self, key: VirtualKey) -> bool {
+        let code = key_code(key);
+
+        self.sources.borrow().iter().any(|source| {
+            source
+                .device
+                .supported_keys()
+                .is_some_and(|keys| keys.contains(code))
+                && source
+                    .device
+                    .get_key_state()
+                    .is_ok_and(|state| state.contains(code))
+        })
+    }
+
+    fn get_cursor(&self) -> Result<(i32, i32)> {
+        Ok(self.tracker.borrow().cursor)
+    }
+
+    fn set_cursor(&self, x: i32, y: i32) -> Result<()> {
+        let mut tracker = self.tracker.borrow_mut();
+        let (x, y) = tracker.clamp((x, y));
+
+        self.pointer.borrow_mut().emit(&[
+            InputEvent::new(EventType::ABSOLUTE.0, AbsoluteAxisCode::ABS_X.0, x),
+            InputEvent::new(EventType::ABSOLUTE.0, AbsoluteAxisCode::ABS_Y.0, y),
+        ])?;
+
+        tracker.cursor = (x, y);
+        tracker.is_lost = false;
+
+        Ok(())
+    }
+
+    fn send_key_down(&self, key: VirtualKey) -> Result<()> {
+        self.send_key(key, 1)
+    }
+
+    fn send_key_up(&self, key: VirtualKey) -> Result<()> {
+        self.send_key(key, 0)
+    }
+
+    fn send_mouse_down(&self, button: MouseButton) -> Result<()> {
+        self.send_key(button.into(), 1)
+    }
+
+    fn send_mouse_up(&self, button: MouseButton) -> Result<()> {
+        self.send_key(button.into(), 0)
+    }
+
+    fn message_box(&self, msg: &str, title: &str) -> Result<()> {
+        super::message_box(msg, title)
+    }
+
+    // the cursor is put where it is thought to be whenever that is unsure, starting in the middle of
+    // the desktop
+    fn update(&self) -> Result<()> {
+        let (x, y) = {
+            let mut tracker = self.tracker.borrow_mut();
+            tracker.follow(&mut self.sources.borrow_mut())?;
+
+            if !tracker.is_lost {
+                return Ok(());
+            }
+
+            tracker.cursor
+        };
+
+        self.set_cursor(x, y)
+    }
+
+    // windows are not visible from here, so the game is assumed to be fullscreen
+    fn find_window(&self, _name: &str) -> Result<Option<ClientRect>> {
+        Ok(None)
+    }
+}
+
+impl Source {
+    // devices which can neither press keys nor move the cursor are of no interest
+    fn new(device: RawDevice) -> std::io::Result<Option<Self>> {
+        let has_keys = device.supported_keys().is_some();
+        let has_rel = device.supported_relative_axes().is_some();
+        let has_abs = device.supported_absolute_axes().is_some();
+
+        if !(has_keys || has_rel || has_abs) {
+            return Ok(None);
+        }
+
+        let (mut abs_x, mut abs_y) = (None, None);
+
+        if has_abs {
+            for (code, info) in device.get_absinfo()? {
+                match code {
+                    AbsoluteAxisCode::ABS_X => abs_x = Some((info.minimum(), info.maximum())),
+                    AbsoluteAxisCode::ABS_Y => abs_y = Some((info.minimum(), info.maximum())),
+                    _ => (),
+                }
+            }
+        }
+
+        Ok(Some(Self {
+            device,
+            abs_x,
+            abs_y,
+        }))
+    }
+}
+
+impl Events for RawDevice {
+    fn fetch(&mut self) -> std::io::Result<Vec<InputEvent>> {
+        match self.fetch_events() {
+            Ok(events) => Ok(events.collect()),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Tracker {
+    fn new(desktop: (i32, i32)) -> Self {
+        Self {
+            desktop,
+            cursor: (desktop.0 / 2, desktop.1 / 2),
+            is_lost: true,
+        }
+    }
+
+    fn follow<E: Events>(&mut self, sources: &mut [Source<E>]) -> std::io::Result<()> {
+        let scale = |value, (min, max): (i32, i32), len: i32| {
+            ((value - min) as i64 * (len - 1) as i64 / (max - min).max(1) as i64) as i32
+        };
+
+        for source in sources {
+            for event in source.device.fetch()? {
+                match event.destructure() {
+                    // what moved in the meantime is lost
+                    EventSummary::Synchronization(_, SynchronizationCode::SYN_DROPPED, _) => {
+                        self.is_lost = true;
+                    }
+                    EventSummary::RelativeAxis(_, RelativeAxisCode::REL_X, dx) => {
+                        self.cursor.0 += dx;
+                    }
+                    EventSummary::RelativeAxis(_, RelativeAxisCode::REL_Y, dy) => {
+                        self.cursor.1 += dy;
+                    }
+                    EventSummary::AbsoluteAxis(_, AbsoluteAxisCode::ABS_X, x) => {
+                        if let Some(range) = source.abs_x {
+                            self.cursor.0 = scale(x, range, self.desktop.0);
+                        }
+                    }
+                    EventSummary::AbsoluteAxis(_, AbsoluteAxisCode::ABS_Y, y) => {
+                        if let Some(range) = source.abs_y {
+                            self.cursor.1 = scale(y, range, self.desktop.1);
+                        }
+                    }
+                    _ => (),
+                }
+
+                self.cursor = self.clamp(self.cursor);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn clamp(&self, (x, y): (i32, i32)) -> (i32, i32) {
+        (
+            x.clamp(0, self.desktop.0 - 1),
+            y.clamp(0, self.desktop.1 - 1),
+        )
+    }
+}
+
+const fn key_code(key: VirtualKey) -> KeyCode {
+    use VirtualKey as V;
+
+    match key {
+        V::LButton => KeyCode::BTN_LEFT,
+        V::RButton => KeyCode::BTN_RIGHT,
+        V::MButton => KeyCode::BTN_MIDDLE,
+        V::XButton1 => KeyCode::BTN_SIDE,
+        V::XButton2 => KeyCode::BTN_EXTRA,
+        V::Backspace => KeyCode::KEY_BACKSPACE,
+        V::Tab => KeyCode::KEY_TAB,
+        V::Return => KeyCode::KEY_ENTER,
+        V::Escape => KeyCode::KEY_ESC,
+        V::Space => KeyCode::KEY_SPACE,
+        V::Grave => KeyCode::KEY_GRAVE,
+        V::LShift => KeyCode::KEY_LEFTSHIFT,
+        V::RShift => KeyCode::KEY_RIGHTSHIFT,
+        V::LControl => KeyCode::KEY_LEFTCTRL,
+        V::RControl => KeyCode::KEY_RIGHTCTRL,
+        V::LAlt => KeyCode::KEY_LEFTALT,
+        V::RAlt => KeyCode::KEY_RIGHTALT,
+        V::A => KeyCode::KEY_A,
+        V::B => KeyCode::KEY_B,
+        V::C => KeyCode::KEY_C,
+        V::D => KeyCode::KEY_D,
+        V::E => KeyCode::KEY_E,
+        V::F => KeyCode::KEY_F,
+        V::G => KeyCode::KEY_G,
+        V::H => KeyCode::KEY_H,
+        V::I => KeyCode::KEY_I,
+        V::J => KeyCode::KEY_J,
+        V::K => KeyCode::KEY_K,
+        V::L => KeyCode::KEY_L,
+        V::M => KeyCode::KEY_M,
+        V::N => KeyCode::KEY_N,
+        V::O => KeyCode::KEY_O,
+        V::P => KeyCode::KEY_P,
+        V::Q => KeyCode::KEY_Q,
+        V::R => KeyCode::KEY_R,
+        V::S => KeyCode::KEY_S,
+        V::T => KeyCode::KEY_T,
+        V::U => KeyCode::KEY_U,
+        V::V => KeyCode::KEY_V,
+        V::W => KeyCode::KEY_W,
+        V::X => KeyCode::KEY_X,
+        V::Y => KeyCode::KEY_Y,
+        V::Z => KeyCode::KEY_Z,
+        V::Num0 => KeyCode::KEY_0,
+        V::Num1 => KeyCode::KEY_1,
+        V::Num2 => KeyCode::KEY_2,
+        V::Num3 => KeyCode::KEY_3,
+        V::Num4 => KeyCode::KEY_4,
+        V::Num5 => KeyCode::KEY_5,
+        V::Num6 => KeyCode::KEY_6,
+        V::Num7 => KeyCode::KEY_7,
+        V::Num8 => KeyCode::KEY_8,
+        V::Num9 => KeyCode::KEY_9,
+        V::F1 => KeyCode::KEY_F1,
+        V::F2 => KeyCode::KEY_F2,
+        V::F3 => KeyCode::KEY_F3,
+        V::F4 => KeyCode::KEY_F4,
+        V::F5 => KeyCode::KEY_F5,
+        V::F6 => KeyCode::KEY_F6,
+        V::F7 => KeyCode::KEY_F7,
+        V::F8 => KeyCode::KEY_F8,
+        V::F9 => KeyCode::KEY_F9,
+        V::F10 => KeyCode::KEY_F10,
+        V::F11 => KeyCode::KEY_F11,
+        V::F12 => KeyCode::KEY_F12,
+    }
+}
+
+// these need write access to /dev/uinput and read access to /dev/input/event*
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{thread, time::Duration};
+
+    const DESKTOP: (i32, i32) = (1920, 1080);
+
+    // udev needs a moment to create the device nodes of a new virtual device
+    fn settle() {
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    #[test]
+    #[ignore = "requires /dev/uinput"]
+    fn is_down_follows_virtual_keyboard() -> Result<()> {
+        let mut keys = AttributeSet::<KeyCode>::new();
+        keys.insert(KeyCode::KEY_Z);
+
+        let mut keyboard = VirtualDevice::builder()?
+            .name("mctool test keyboard")
+            .with_keys(&keys)?
+            .build()?;
+
+        settle();
+        let backend = Uinput::new(DESKTOP)?;
+
+        keyboard.emit(&[InputEvent::new(EventType::KEY.0, KeyCode::KEY_Z.0, 1)])?;
+        assert!(backend.is_down(VirtualKey::Z));
+        assert!(!backend.is_down(VirtualKey::X));
+
+        keyboard.emit(&[InputEvent::new(EventType::KEY.0, KeyCode::KEY_Z.0, 0)])?;
+        assert!(!backend.is_down(VirtualKey::Z));
+
+        Ok(())
+    }
+
+    // `observer` is created after `injector`, so it sees the virtual devices of `injector`
+    // as if they were physical ones
+    #[test]
+    #[ignore = "requires /dev/uinput"]
+    fn injected_inputs_are_observed() -> Result<()> {
+        let injector = Uinput::new(DESKTOP)?;
+        settle();
+        let observer = Uinput::new(DESKTOP)?;
+
+        injector.send_mouse_down(MouseButton::Left)?;
+        injector.send_key_down(VirtualKey::Space)?;
+        assert!(observer.is_down(VirtualKey::LButton));
+        assert!(observer.is_down(VirtualKey::Space));
+
+        injector.send_mouse_up(MouseButton::Left)?;
+        injector.send_key_up(VirtualKey::Space)?;
+        assert!(!observer.is_down(VirtualKey::LButton));
+        assert!(!observer.is_down(VirtualKey::Space));
+
+        injector.set_cursor(783, 339)?;
+        observer.update()?;
+        assert_eq!(injector.get_cursor()?, (783, 339));
+        assert_eq!(observer.get_cursor()?, (783, 339));
+
+        injector.set_cursor(-10, 5000)?;
+        observer.update()?;
+        assert_eq!(observer.get_cursor()?, (0, DESKTOP.1 - 1));
+
+        Ok(())
+    }
+
+    // batches of events, one per fetch
+    struct Fake(Vec<Vec<InputEvent>>);
+
+    impl Events for Fake {
+        fn fetch(&mut self) -> std::io::Result<Vec<InputEvent>> {
+            Ok(match self.0.is_empty() {
+                true => Vec::new(),
+                false => self.0.remove(0),
+            })
+        }
+    }
+
+    fn rel(code: RelativeAxisCode, value: i32) -> InputEvent {
+        InputEvent::new(EventType::RELATIVE.0, code.0, value)
+    }
+
+    fn abs(code: AbsoluteAxisCode, value: i32) -> InputEvent {
+        InputEvent::new(EventType::ABSOLUTE.0, code.0, value)
+    }
+
+    #[test]
+    fn tracks_the_cursor_across_devices() -> std::io::Result<()> {
+        let mouse = Source {
+            device: Fake(vec![
+                vec![
+                    rel(RelativeAxisCode::REL_X, 100),
+                    rel(RelativeAxisCode::REL_Y, -40),
+                ],
+                vec![
+                    InputEvent::new(
+                        EventType::SYNCHRONIZATION.0,
+                        SynchronizationCode::SYN_DROPPED.0,
+                        0,
+                    ),
+                    rel(RelativeAxisCode::REL_X, -5000),
+                ],
+            ]),
+            abs_x: None,
+            abs_y: None,
+        };
+        let tablet = Source {
+            device: Fake(vec![
+                Vec::new(),
+                Vec::new(),
+                vec![
+                    abs(AbsoluteAxisCode::ABS_X, 32767),
+                    abs(AbsoluteAxisCode::ABS_Y, 0),
+                ],
+            ]),
+            abs_x: Some((0, 32767)),
+            abs_y: Some((0, 32767)),
+        };
+        let mut sources = [mouse, tablet];
+        let mut tracker = Tracker::new(DESKTOP);
+
+        // unknown until warped there
+        assert!(tracker.is_lost);
+        tracker.is_lost = false;
+
+        tracker.follow(&mut sources)?;
+        assert_eq!(tracker.cursor, (1060, 500));
+        assert!(!tracker.is_lost);
+
+        tracker.follow(&mut sources)?;
+        assert_eq!(tracker.cursor, (0, 500));
+        assert!(tracker.is_lost);
+
+        tracker.follow(&mut sources)?;
+        assert_eq!(tracker.cursor, (DESKTOP.0 - 1, 0));
+
+        Ok(())
+    }
+}
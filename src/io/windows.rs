@@ -0,0 +1,345 @@
+use super::{ClientRect, InputBackend, MouseButton, VirtualKey};
+use crate::map_err_anyhow::MapErrAnyhow;
+use anyhow::{Result, bail};
+use image::RgbImage;
+use windows::Win32::Foundation::{self as foundation, HWND, LPARAM, POINT, RECT};
+use windows::Win32::Graphics::Gdi;
+use windows::Win32::System::Threading as threading;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    self as kam, INPUT, INPUT_0, KEYBD_EVENT_FLAGS, KEYBDINPUT, MOUSE_EVENT_FLAGS, MOUSEINPUT,
+    VIRTUAL_KEY,
+};
+use windows::Win32::UI::WindowsAndMessaging as wam;
+use windows::core::{BOOL, PWSTR};
+
+pub struct Win32;
+
+impl InputBackend for Win32 {
+    fn is_down(&self, key: VirtualKey) -> bool {
+        unsafe { kam::GetAsyncKeyState(vkey(key).0 as i32) }.cast_unsigned() >> 15 != 0
+    }
+
+    fn get_cursor(&self) -> Result<(i32, i32)> {
+        let mut point = POINT::default();
+
+        unsafe {
+            wam::GetCursorPos(&mut point)?;
+        }
+
+        Ok((point.x, point.y))
+    }
+
+    fn set_cursor(&self, x: i32, y: i32) -> Result<()> {
+        unsafe { wam::SetCursorPos(x, y) }.map_err_anyhow()
+    }
+
+    fn send_key_down(&self, key: VirtualKey) -> Result<()> {
+        send_inputs(&[keyboard_input(key, KEYBD_EVENT_FLAGS::default())]);
+        Ok(())
+    }
+
+    fn send_key_up(&self, key: VirtualKey) -> Result<()> {
+        send_inputs(&[keyboard_input(key, kam::KEYEVENTF_KEYUP)]);
+        Ok(())
+    }
+
+    fn send_mouse_down(&self, button: MouseButton) -> Result<()> {
+        let (flag_down, _) = mouse_flags(button);
+        send_inputs(&[mouse_input(flag_down)]);
+        Ok(())
+    }
+
+    fn send_mouse_up(&self, button: MouseButton) -> Result<()> {
+        let (_, flag_up) = mouse_flags(button);
+        send_inputs(&[mouse_input(flag_up)]);
+        Ok(())
+    }
+
+    fn send_mouse(&self, button: MouseButton) -> Result<()> {
+        let (flag_down, flag_up) = mouse_flags(button);
+        send_inputs(&[mouse_input(flag_down), mouse_input(flag_up)]);
+        Ok(())
+    }
+
+    fn message_box(&self, msg: &str, title: &str) -> Result<()> {
+        message_box(msg, title)
+    }
+
+    fn find_window(&self, name: &str) -> Result<Option<ClientRect>> {
+        struct Search {
+            name: String,
+            found: Option<HWND>,
+        }
+
+        unsafe extern "system" fn callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+            let search = unsafe { &mut *(lparam.0 as *mut Search) };
+
+            let is_match = unsafe { wam::IsWindowVisible(hwnd) }.as_bool()
+                && (window_title(hwnd).to_lowercase().contains(&search.name)
+                    || process_name(hwnd).to_lowercase().contains(&search.name));
+
+            if is_match {
+                search.found = Some(hwnd);
+            }
+
+            // stops enumerating once found
+            (!is_match).into()
+        }
+
+        let mut search = Search {
+            name: name.to_lowercase(),
+            found: None,
+        };
+
+        // reports an error when stopped by the callback, which is not one here
+        let _ = unsafe { wam::EnumWindows(Some(callback), LPARAM(&raw mut search as isize)) };
+
+        search.found.map(client_rect).transpose()
+    }
+
+    fn capture(&self, x: i32, y: i32, width: u32, height: u32) -> Result<Option<RgbImage>> {
+        let (cx, cy) = (width as i32, height as i32);
+        let mut bgra = vec![0u8; width as usize * height as usize * 4];
+        // top-down, 32 bits per pixel in the order BGRX
+        let mut info = Gdi::BITMAPINFO {
+            bmiHeader: Gdi::BITMAPINFOHEADER {
+                biSize: size_of::<Gdi::BITMAPINFOHEADER>() as u32,
+                biWidth: cx,
+                biHeight: -cy,
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: Gdi::BI_RGB.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let (copied, lines) = unsafe {
+            let screen = Gdi::GetDC(None);
+            let memory = Gdi::CreateCompatibleDC(Some(screen));
+            let bitmap = Gdi::CreateCompatibleBitmap(screen, cx, cy);
+            let previous = Gdi::SelectObject(memory, bitmap.into());
+
+            let copied = Gdi::BitBlt(memory, 0, 0, cx, cy, Some(screen), x, y, Gdi::SRCCOPY);
+            Gdi::SelectObject(memory, previous);
+            let lines = Gdi::GetDIBits(
+                memory,
+                bitmap,
+                0,
+                height,
+                Some(bgra.as_mut_ptr().cast()),
+                &raw mut info,
+                Gdi::DIB_RGB_COLORS,
+            );
+
+            let _ = Gdi::DeleteObject(bitmap.into());
+            let _ = Gdi::DeleteDC(memory);
+            Gdi::ReleaseDC(None, screen);
+
+            (copied, lines)
+        };
+
+        copied?;
+        if lines != cy {
+            bail!("failed to read the screen");
+        }
+
+        let pixels = bgra
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[2], pixel[1], pixel[0]])
+            .collect();
+
+        Ok(RgbImage::from_raw(width, height, pixels))
+    }
+}
+
+fn window_title(hwnd: HWND) -> String {
+    let mut buffer = [0; 512];
+    let len = unsafe { wam::GetWindowTextW(hwnd, &mut buffer) };
+
+    String::from_utf16_lossy(&buffer[..len.max(0) as usize])
+}
+
+// the file name of the executable, e.g. `javaw.exe`
+fn process_name(hwnd: HWND) -> String {
+    let mut pid = 0;
+    unsafe { wam::GetWindowThreadProcessId(hwnd, Some(&raw mut pid)) };
+
+    let Ok(process) = (unsafe {
+        threading::OpenProcess(threading::PROCESS_QUERY_LIMITED_INFORMATION, false, pid)
+    }) else {
+        return String::new();
+    };
+
+    let mut buffer = [0; 512];
+    let mut len = buffer.len() as u32;
+    let result = unsafe {
+        threading::QueryFullProcessImageNameW(
+            process,
+            threading::PROCESS_NAME_WIN32,
+            PWSTR(buffer.as_mut_ptr()),
+            &raw mut len,
+        )
+    };
+    let _ = unsafe { foundation::CloseHandle(process) };
+
+    match result {
+        Ok(()) => String::from_utf16_lossy(&buffer[..len as usize])
+            .rsplit('\\')
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+fn client_rect(hwnd: HWND) -> Result<ClientRect> {
+    let mut rect = RECT::default();
+    let mut origin = POINT::default();
+
+    unsafe {
+        wam::GetClientRect(hwnd, &raw mut rect)?;
+        Gdi::ClientToScreen(hwnd, &raw mut origin).ok()?;
+    }
+
+    Ok(ClientRect {
+        x: origin.x,
+        y: origin.y,
+        width: rect.right - rect.left,
+        height: rect.bottom - rect.top,
+    })
+}
+
+pub fn message_box<V1, V2>(msg: V1, title: V2) -> Result<()>
+where
+    V1: Into<Vec<u8>>,
+    V2: Into<Vec<u8>>,
+{
+    use std::ffi::CString;
+    use windows::core::PCSTR;
+
+    let msg = CString::new(msg)?;
+    let title = CString::new(title)?;
+
+    unsafe {
+        wam::MessageBoxA(
+            None,
+            PCSTR::from_raw(msg.as_bytes().as_ptr()),
+            PCSTR::from_raw(title.as_bytes().as_ptr()),
+            wam::MB_ICONINFORMATION,
+        );
+    }
+
+    Ok(())
+}
+
+const fn vkey(key: VirtualKey) -> VIRTUAL_KEY {
+    use VirtualKey as V;
+
+    match key {
+        V::LButton => kam::VK_LBUTTON,
+        V::RButton => kam::VK_RBUTTON,
+        V::MButton => kam::VK_MBUTTON,
+        V::XButton1 => kam::VK_XBUTTON1,
+        V::XButton2 => kam::VK_XBUTTON2,
+        V::Backspace => kam::VK_BACK,
+        V::Tab => kam::VK_TAB,
+        V::Return => kam::VK_RETURN,
+        V::Escape => kam::VK_ESCAPE,
+        V::Space => kam::VK_SPACE,
+        V::Grave => kam::VK_OEM_3,
+        V::LShift => kam::VK_LSHIFT,
+        V::RShift => kam::VK_RSHIFT,
+        V::LControl => kam::VK_LCONTROL,
+        V::RControl => kam::VK_RCONTROL,
+        V::LAlt => kam::VK_LMENU,
+        V::RAlt => kam::VK_RMENU,
+        V::A => kam::VK_A,
+        V::B => kam::VK_B,
+        V::C => kam::VK_C,
+        V::D => kam::VK_D,
+        V::E => kam::VK_E,
+        V::F => kam::VK_F,
+        V::G => kam::VK_G,
+        V::H => kam::VK_H,
+        V::I => kam::VK_I,
+        V::J => kam::VK_J,
+        V::K => kam::VK_K,
+        V::L => kam::VK_L,
+        V::M => kam::VK_M,
+        V::N => kam::VK_N,
+        V::O => kam::VK_O,
+        V::P => kam::VK_P,
+        V::Q => kam::VK_Q,
+        V::R => kam::VK_R,
+        V::S => kam::VK_S,
+        V::T => kam::VK_T,
+        V::U => kam::VK_U,
+        V::V => kam::VK_V,
+        V::W => kam::VK_W,
+        V::X => kam::VK_X,
+        V::Y => kam::VK_Y,
+        V::Z => kam::VK_Z,
+        V::Num0 => kam::VK_0,
+        V::Num1 => kam::VK_1,
+        V::Num2 => kam::VK_2,
+        V::Num3 => kam::VK_3,
+        V::Num4 => kam::VK_4,
+        V::Num5 => kam::VK_5,
+        V::Num6 => kam::VK_6,
+        V::Num7 => kam::VK_7,
+        V::Num8 => kam::VK_8,
+        V::Num9 => kam::VK_9,
+        V::F1 => kam::VK_F1,
+        V::F2 => kam::VK_F2,
+        V::F3 => kam::VK_F3,
+        V::F4 => kam::VK_F4,
+        V::F5 => kam::VK_F5,
+        V::F6 => kam::VK_F6,
+        V::F7 => kam::VK_F7,
+        V::F8 => kam::VK_F8,
+        V::F9 => kam::VK_F9,
+        V::F10 => kam::VK_F10,
+        V::F11 => kam::VK_F11,
+        V::F12 => kam::VK_F12,
+    }
+}
+
+const fn mouse_flags(button: MouseButton) -> (MOUSE_EVENT_FLAGS, MOUSE_EVENT_FLAGS) {
+    match button {
+        MouseButton::Left => (kam::MOUSEEVENTF_LEFTDOWN, kam::MOUSEEVENTF_LEFTUP),
+        MouseButton::Right => (kam::MOUSEEVENTF_RIGHTDOWN, kam::MOUSEEVENTF_RIGHTUP),
+        MouseButton::Middle => (kam::MOUSEEVENTF_MIDDLEDOWN, kam::MOUSEEVENTF_MIDDLEUP),
+    }
+}
+
+fn keyboard_input(key: VirtualKey, flags: KEYBD_EVENT_FLAGS) -> INPUT {
+    INPUT {
+        r#type: kam::INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: vkey(key),
+                dwFlags: flags,
+                ..Default::default()
+            },
+        },
+    }
+}
+
+fn mouse_input(flags: MOUSE_EVENT_FLAGS) -> INPUT {
+    INPUT {
+        r#type: kam::INPUT_MOUSE,
+        Anonymous: INPUT_0 {
+            mi: MOUSEINPUT {
+                dwFlags: flags,
+                ..Default::default()
+            },
+        },
+    }
+}
+
+fn send_inputs(inputs: &[INPUT]) {
+    unsafe {
+        kam::SendInput(inputs, size_of::<INPUT>() as i32);
+    }
+}
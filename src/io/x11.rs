@@ -0,0 +1,552 @@
+use super::{ClientRect, InputBackend, MouseButton, VirtualKey};
+use anyhow::{Result, anyhow};
+use image::RgbImage;
+use std::collections::HashMap;
+use x11rb::{
+    CURRENT_TIME, NONE,
+    connection::Connection,
+    protocol::{
+        xinput::{ConnectionExt as _, DeviceId},
+        xproto::{self, AtomEnum, ConnectionExt as _, ImageFormat, Keycode, Window},
+        xtest::ConnectionExt as _,
+    },
+    rust_connection::RustConnection,
+};
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        _NET_CLIENT_LIST,
+        _NET_WM_NAME,
+        _NET_WM_PID,
+        UTF8_STRING,
+    }
+}
+
+// XTest for synthetic inputs, the core protocol for the cursor and the keyboard, and XInput2 for
+// mouse buttons (the core button mask does not cover the side buttons)
+pub struct X11 {
+    conn: RustConnection,
+    root: Window,
+    pointer: DeviceId,
+    keycodes: HashMap<VirtualKey, Keycode>,
+    atoms: Atoms,
+}
+
+impl X11 {
+    pub fn new() -> Result<Self> {
+        let (conn, screen) = x11rb::connect(None)?;
+        let root = conn.setup().roots[screen].root;
+
+        conn.xtest_get_version(2, 2)?.reply()?;
+        conn.xinput_xi_query_version(2, 0)?.reply()?;
+
+        let pointer = conn.xinput_xi_get_client_pointer(NONE)?.reply()?.deviceid;
+        let keycodes = Self::keycodes(&conn)?;
+        let atoms = Atoms::new(&conn)?.reply()?;
+
+        Ok(Self {
+            conn,
+            root,
+            pointer,
+            keycodes,
+            atoms,
+        })
+    }
+
+    fn keycodes(conn: &RustConnection) -> Result<HashMap<VirtualKey, Keycode>> {
+        let setup = conn.setup();
+        let first = setup.min_keycode;
+        let count = setup.max_keycode - setup.min_keycode + 1;
+        let mapping = conn.get_keyboard_mapping(first, count)?.reply()?;
+
+        let keycodes = VirtualKey::ALL
+            .iter()
+            .filter_map(|key| {
+                let keysym = keysym(*key)?;
+                let index = mapping
+                    .keysyms
+                    .chunks(mapping.keysyms_per_keycode.into())
+                    .position(|keysyms| keysyms.contains(&keysym))?;
+
+                Some((*key, first + index as Keycode))
+            })
+            .collect();
+
+        Ok(keycodes)
+    }
+
+    fn keycode(&self, key: VirtualKey) -> Result<Keycode> {
+        self.keycodes
+            .get(&key)
+            .copied()
+            .ok_or_else(|| anyhow!("{key:?} is not on the keyboard mapping"))
+    }
+
+    // top-level windows, from the window manager if there is one
+    fn windows(&self) -> Result<Vec<Window>> {
+        let managed = self
+            .conn
+            .get_property(
+                false,
+                self.root,
+                self.atoms._NET_CLIENT_LIST,
+                AtomEnum::WINDOW,
+                0,
+                u32::MAX,
+            )?
+            .reply()?;
+
+        match managed.value32() {
+            Some(windows) if managed.value_len != 0 => Ok(windows.collect()),
+            _ => Ok(self.conn.query_tree(self.root)?.reply()?.children),
+        }
+    }
+
+    fn title(&self, window: Window) -> Result<String> {
+        for (property, r#type) in [
+            (self.atoms._NET_WM_NAME, self.atoms.UTF8_STRING),
+            (AtomEnum::WM_NAME.into(), AtomEnum::STRING.into()),
+        ] {
+            let reply = self
+                .conn
+                .get_property(false, window, property, r#type, 0, u32::MAX)?
+                .reply()?;
+
+            if !reply.value.is_empty() {
+                return Ok(String::from_utf8_lossy(&reply.value).into_owned());
+            }
+        }
+
+        Ok(String::new())
+    }
+
+    fn process(&self, window: Window) -> Result<String> {
+        let pid = self
+            .conn
+            .get_property(
+                false,
+                window,
+                self.atoms._NET_WM_PID,
+                AtomEnum::CARDINAL,
+                0,
+                1,
+            )?
+            .reply()?
+            .value32()
+            .and_then(|mut values| values.next());
+
+        let name = pid
+            .and_then(|pid| std::fs::read_to_string(format!("/proc/{pid}/comm")).ok())
+            .unwrap_or_default();
+
+        Ok(name.trim_end().to_string())
+    }
+
+    fn client_rect(&self, window: Window) -> Result<ClientRect> {
+        let geometry = self.conn.get_geometry(window)?.reply()?;
+        let position = self
+            .conn
+            .translate_coordinates(window, self.root, 0, 0)?
+            .reply()?;
+
+        Ok(ClientRect {
+            x: position.dst_x.into(),
+            y: position.dst_y.into(),
+            width: geometry.width.into(),
+            height: geometry.height.into(),
+        })
+    }
+
+    fn fake_input(&self, r#type: u8, detail: u8) -> Result<()> {
+        self.conn
+            .xtest_fake_input(r#type, detail, CURRENT_TIME, NONE, 0, 0, 0)?;
+        self.conn.flush()?;
+
+        Ok(())
+    }
+}
+
+impl InputBackend for X11 {
+    fn is_down(&self, key: VirtualKey) -> bool {
+        match button(key) {
+            Some(button) => self
+                .conn
+                .xinput_xi_query_pointer(self.root, self.pointer)
+                .ok()
+                .and_then(|cookie| cookie.reply().ok())
+                .and_then(|reply| reply.buttons.get(button as usize / 32).copied())
+                .is_some_and(|mask| mask >> (button % 32) & 1 != 0),
+            None => self
+                .keycodes
+                .get(&key)
+                .zip(
+                    self.conn
+                        .query_keymap()
+                        .ok()
+                        .and_then(|cookie| cookie.reply().ok()),
+                )
+                .is_some_and(|(keycode, keymap)| {
+                    keymap.keys[*keycode as usize / 8] >> (keycode % 8) & 1 != 0
+                }),
+        }
+    }
+
+    fn get_cursor(&self) -> Result<(i32, i32)> {
+        let reply = self.conn.query_pointer(self.root)?.reply()?;
+        Ok((reply.root_x.into(), reply.root_y.into()))
+    }
+
+    fn set_cursor(&self, x: i32, y: i32) -> Result<()> {
+        self.conn
+            .warp_pointer(NONE, self.root, 0, 0, 0, 0, x as i16, y as i16)?;
+        self.conn.flush()?;
+
+        Ok(())
+    }
+
+    fn send_key_down(&self, key: VirtualKey) -> Result<()> {
+        match button(key) {
+            Some(button) => self.fake_input(xproto::BUTTON_PRESS_EVENT, button),
+            None => self.fake_input(xproto::KEY_PRESS_EVENT, self.keycode(key)?),
+        }
+    }
+
+    fn send_key_up(&self, key: VirtualKey) -> Result<()> {
+        match button(key) {
+            Some(button) => self.fake_input(xproto::BUTTON_RELEASE_EVENT, button),
+            None => self.fake_input(xproto::KEY_RELEASE_EVENT, self.keycode(key)?),
+        }
+    }
+
+    fn send_mouse_down(&self, button: MouseButton) -> Result<()> {
+        self.send_key_down(button.into())
+    }
+
+    fn send_mouse_up(&self, button: MouseButton) -> Result<()> {
+        self.send_key_up(button.into())
+    }
+
+    fn message_box(&self, msg: &str, title: &str) -> Result<()> {
+        super::message_box(msg, title)
+    }
+
+    fn find_window(&self, name: &str) -> Result<Option<ClientRect>> {
+        let name = name.to_lowercase();
+
+        for window in self.windows()? {
+            let attributes = self.conn.get_window_attributes(window)?.reply()?;
+
+            if attributes.map_state != xproto::MapState::VIEWABLE {
+                continue;
+            }
+
+            if self.title(window)?.to_lowercase().contains(&name)
+                || self.process(window)?.to_lowercase().contains(&name)
+            {
+                return self.client_rect(window).map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn capture(&self, x: i32, y: i32, width: u32, height: u32) -> Result<Option<RgbImage>> {
+        let reply = self
+            .conn
+            .get_image(
+                ImageFormat::Z_PIXMAP,
+                self.root,
+                x as i16,
+                y as i16,
+                width as u16,
+                height as u16,
+                !0,
+            )?
+            .reply()?;
+
+        // 32 bits per pixel in the order BGRX, as on any TrueColor visual of depth 24 or 32
+        if reply.depth < 24 {
+            return Ok(None);
+        }
+
+        let pixels = reply
+            .data
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[2], pixel[1], pixel[0]])
+            .collect();
+
+        Ok(RgbImage::from_raw(width, height, pixels))
+    }
+}
+
+const fn button(key: VirtualKey) -> Option<u8> {
+    match key {
+        VirtualKey::LButton => Some(1),
+        VirtualKey::MButton => Some(2),
+        VirtualKey::RButton => Some(3),
+        VirtualKey::XButton1 => Some(8),
+        VirtualKey::XButton2 => Some(9),
+        _ => None,
+    }
+}
+
+const fn keysym(key: VirtualKey) -> Option<u32> {
+    use VirtualKey as V;
+
+    let keysym = match key {
+        V::LButton | V::RButton | V::MButton | V::XButton1 | V::XButton2 => return None,
+        V::Backspace => 0xFF08,
+        V::Tab => 0xFF09,
+        V::Return => 0xFF0D,
+        V::Escape => 0xFF1B,
+        V::Space => 0x0020,
+        V::Grave => 0x0060,
+        V::LShift => 0xFFE1,
+        V::RShift => 0xFFE2,
+        V::LControl => 0xFFE3,
+        V::RControl => 0xFFE4,
+        V::LAlt => 0xFFE9,
+        V::RAlt => 0xFFEA,
+        V::A => 0x0061,
+        V::B => 0x0062,
+        V::C => 0x0063,
+        V::D => 0x0064,
+        V::E => 0x0065,
+        V::F => 0x0066,
+        V::G => 0x0067,
+        V::H => 0x0068,
+        V::I => 0x0069,
+        V::J => 0x006A,
+        V::K => 0x006B,
+        V::L => 0x006C,
+        V::M => 0x006D,
+        V::N => 0x006E,
+        V::O => 0x006F,
+        V::P => 0x0070,
+        V::Q => 0x0071,
+        V::R => 0x0072,
+        V::S => 0x0073,
+        V::T => 0x0074,
+        V::U => 0x0075,
+        V::V => 0x0076,
+        V::W => 0x0077,
+        V::X => 0x0078,
+        V::Y => 0x0079,
+        V::Z => 0x007A,
+        V::Num0 => 0x0030,
+        V::Num1 => 0x0031,
+        V::Num2 => 0x0032,
+        V::Num3 => 0x0033,
+        V::Num4 => 0x0034,
+        V::Num5 => 0x0035,
+        V::Num6 => 0x0036,
+        V::Num7 => 0x0037,
+        V::Num8 => 0x0038,
+        V::Num9 => 0x0039,
+        V::F1 => 0xFFBE,
+        V::F2 => 0xFFBF,
+        V::F3 => 0xFFC0,
+        V::F4 => 0xFFC1,
+        V::F5 => 0xFFC2,
+        V::F6 => 0xFFC3,
+        V::F7 => 0xFFC4,
+        V::F8 => 0xFFC5,
+        V::F9 => 0xFFC6,
+        V::F10 => 0xFFC7,
+        V::F11 => 0xFFC8,
+        V::F12 => 0xFFC9,
+    };
+
+    Some(keysym)
+}
+
+// run with `xvfb-run cargo test x11 -- --ignored --test-threads=1`
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::SystemClock,
+        config::Config,
+        grid::{Container, Geometry, Grid},
+        io,
+        layout::Layout,
+        state::{State, detail::Detail},
+    };
+    use std::time::Duration;
+    use x11rb::{
+        protocol::{
+            Event,
+            xproto::{CreateWindowAux, EventMask, PropMode, WindowClass},
+        },
+        wrapper::ConnectionExt as _,
+    };
+
+    // a client which logs the inputs it receives, standing in for the game
+    struct Logger {
+        conn: RustConnection,
+        window: Window,
+    }
+
+    impl Logger {
+        const SIZE: u16 = 400;
+        const TITLE: &str = "Minecraft 1.8.9";
+
+        fn new() -> Result<Self> {
+            let (conn, screen) = x11rb::connect(None)?;
+            let screen = &conn.setup().roots[screen];
+            let window = conn.generate_id()?;
+
+            conn.create_window(
+                screen.root_depth,
+                window,
+                screen.root,
+                0,
+                0,
+                Self::SIZE,
+                Self::SIZE,
+                0,
+                WindowClass::INPUT_OUTPUT,
+                screen.root_visual,
+                &CreateWindowAux::new()
+                    .override_redirect(1)
+                    .event_mask(EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE),
+            )?;
+            conn.change_property8(
+                PropMode::REPLACE,
+                window,
+                AtomEnum::WM_NAME,
+                AtomEnum::STRING,
+                Self::TITLE.as_bytes(),
+            )?;
+            conn.map_window(window)?;
+            conn.sync()?;
+
+            Ok(Self { conn, window })
+        }
+
+        fn next(&self) -> Result<Event> {
+            self.conn.wait_for_event().map_err(Into::into)
+        }
+    }
+
+    #[test]
+    #[ignore = "requires an X server"]
+    fn keys_are_reported_while_held() -> Result<()> {
+        let backend = X11::new()?;
+
+        backend.send_key_down(VirtualKey::Z)?;
+        backend.send_mouse_down(MouseButton::Right)?;
+        assert!(backend.is_down(VirtualKey::Z));
+        assert!(backend.is_down(VirtualKey::RButton));
+        assert!(!backend.is_down(VirtualKey::X));
+        assert!(!backend.is_down(VirtualKey::LButton));
+
+        backend.send_key_up(VirtualKey::Z)?;
+        backend.send_mouse_up(MouseButton::Right)?;
+        assert!(!backend.is_down(VirtualKey::Z));
+        assert!(!backend.is_down(VirtualKey::RButton));
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "requires an X server"]
+    fn clicks_arrive_where_the_cursor_was_set() -> Result<()> {
+        let logger = Logger::new()?;
+        let backend = X11::new()?;
+
+        backend.set_cursor(54, 108)?;
+        assert_eq!(backend.get_cursor()?, (54, 108));
+
+        backend.send_mouse(MouseButton::Left)?;
+
+        match logger.next()? {
+            Event::ButtonPress(event) => {
+                assert_eq!(event.event, logger.window);
+                assert_eq!(event.detail, 1);
+                assert_eq!((event.event_x, event.event_y), (54, 108));
+            }
+            event => panic!("unexpected event: {event:?}"),
+        }
+
+        assert!(matches!(logger.next()?, Event::ButtonRelease(event) if event.detail == 1));
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "requires an X server"]
+    fn plays_a_recipe_into_the_window() -> Result<()> {
+        let logger = Logger::new()?;
+        let dirs = tempfile::TempDir::new()?;
+        let recipe = dirs.path().join("recipes").join("planks");
+        std::fs::create_dir_all(&recipe)?;
+        serde_json::to_writer(
+            std::fs::File::create(recipe.join(io::FILENAME_CLICKS))?,
+            &[Grid::Table(0, 0)],
+        )?;
+
+        let config = Config {
+            screenshots: dirs.path().join("screenshots"),
+            recipes: dirs.path().join("recipes"),
+            items: dirs.path().join("items"),
+            ..Default::default()
+        };
+        let mut state = State::new(Box::new(X11::new()?), Box::new(SystemClock), &config)?;
+        // the player, pressing `play`
+        let player = X11::new()?;
+
+        player.send_key_down(VirtualKey::G)?;
+        state.step()?;
+        player.send_key_up(VirtualKey::G)?;
+        assert!(matches!(state.detail(), Detail::Playing { .. }));
+
+        for _ in 0..1000 {
+            if matches!(state.detail(), Detail::Idle) {
+                break;
+            }
+
+            std::thread::sleep(Duration::from_millis(1));
+            state.step()?;
+        }
+
+        assert!(matches!(state.detail(), Detail::Idle));
+
+        let size = (Logger::SIZE.into(), Logger::SIZE.into());
+        let geometry = Geometry::new(
+            &Layout::new(size, config.game.gui_scale),
+            Container::CraftingTable,
+        );
+        let (x, y) = Grid::Table(0, 0).coord(&geometry).unwrap().into();
+
+        match logger.next()? {
+            Event::ButtonPress(event) => {
+                assert_eq!(event.event, logger.window);
+                assert_eq!((event.event_x.into(), event.event_y.into()), (x, y));
+            }
+            event => panic!("unexpected event: {event:?}"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "requires an X server"]
+    fn finds_window_by_title() -> Result<()> {
+        let _logger = Logger::new()?;
+        let backend = X11::new()?;
+
+        assert_eq!(
+            backend.find_window("minecraft")?,
+            Some(ClientRect {
+                x: 0,
+                y: 0,
+                width: Logger::SIZE.into(),
+                height: Logger::SIZE.into(),
+            })
+        );
+        assert_eq!(backend.find_window("terraria")?, None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,441 @@
+use crate::{
+    coord::Coord,
+    grid::{self, Container, Grid, Picture},
+    recipe::Recipe,
+    verify,
+};
+use anyhow::{Result, bail};
+use image::{Rgb, RgbImage, RgbaImage, imageops};
+use std::path::{Path, PathBuf};
+
+// the background of a slot, over which the textures are drawn
+const BACKGROUND: Rgb<u8> = Rgb([139, 139, 139]);
+
+// the digits of the font of the game, 5x7 pixels each from the top-left
+const DIGITS: [[&str; 7]; 10] = [
+    [
+        ".###.", "#...#", "#..##", "#.#.#", "##..#", "#...#", ".###.",
+    ],
+    [
+        "..#..", ".##..", "..#..", "..#..", "..#..", "..#..", "#####",
+    ],
+    [
+        ".###.", "#...#", "....#", "..##.", ".#...", "#...#", "#####",
+    ],
+    [
+        ".###.", "#...#", "....#", "..##.", "....#", "#...#", ".###.",
+    ],
+    [
+        "...##", "..#.#", ".#..#", "#...#", "#####", "....#", "....#",
+    ],
+    [
+        "#####", "#....", "####.", "....#", "....#", "#...#", ".###.",
+    ],
+    [
+        "..##.", ".#...", "#....", "####.", "#...#", "#...#", ".###.",
+    ],
+    [
+        "#####", "#...#", "....#", "...#.", "..#..", "..#..", "..#..",
+    ],
+    [
+        ".###.", "#...#", "#...#", ".###.", "#...#", "#...#", ".###.",
+    ],
+    [
+        ".###.", "#...#", "#...#", ".####", "....#", "...#.", ".##..",
+    ],
+];
+
+// items known by how their icons look, from the textures of the game (`textures/`, 16x16 or any
+// multiple) and from slots learned on the screen (`slots/`), each named after the ID of the item.
+// several pictures of the same item are told apart by a suffix, e.g. `oak_log.2.png`.
+#[derive(Default)]
+pub struct Library {
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    // the pictures which could not be read, with why
+    skipped: Vec<String>,
+    // counts what was learned, so that whatever was classified before can be told to be stale
+    revision: usize,
+}
+
+struct Entry {
+    id: String,
+    hash: u64,
+    colour: [i32; 3],
+}
+
+// what a slot holds, where the item is unknown if it is not in the library
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stack {
+    pub item: Option<String>,
+    pub count: u32,
+}
+
+impl Library {
+    pub const TEXTURES: &str = "textures";
+    pub const SLOTS: &str = "slots";
+
+    // an empty library if there is no such directory
+    pub fn load(dir: impl AsRef<Path>) -> Self {
+        let dir = dir.as_ref();
+        let mut entries = Vec::new();
+        let mut skipped = Vec::new();
+
+        for (subdir, is_texture) in [(Self::TEXTURES, true), (Self::SLOTS, false)] {
+            let Ok(read_dir) = std::fs::read_dir(dir.join(subdir)) else {
+                continue;
+            };
+
+            for path in read_dir.filter_map(|entry| Some(entry.ok()?.path())) {
+                let Some(id) = id(&path) else {
+                    continue;
+                };
+
+                let image = match image::open(&path) {
+                    Ok(image) => image,
+                    Err(e) => {
+                        skipped.push(format!("{}: {e}", path.display()));
+                        continue;
+                    }
+                };
+                let top = match is_texture {
+                    true => texture_top(&image.into_rgba8()),
+                    false => slot_top(&image.into_rgb8()),
+                };
+
+                entries.push(Entry::new(id, &top));
+            }
+        }
+
+        entries.sort_unstable_by(|a, b| a.id.cmp(&b.id));
+
+        Self {
+            dir: dir.to_path_buf(),
+            entries,
+            skipped,
+            revision: 0,
+        }
+    }
+
+    pub fn skipped(&self) -> &[String] {
+        &self.skipped
+    }
+
+    pub const fn revision(&self) -> usize {
+        self.revision
+    }
+
+    // nothing for an empty slot
+    pub fn classify(&self, slot: &RgbImage) -> Option<Stack> {
+        let scale = (slot.width() / 18).max(1);
+
+        if grid::is_empty(
+            &imageops::crop_imm(slot, scale, scale, 16 * scale, 16 * scale).to_image(),
+        ) {
+            return None;
+        }
+
+        let entry = Entry::new(String::new(), &slot_top(slot));
+        let item = self
+            .entries
+            .iter()
+            .filter(|known| {
+                (0..3).all(|i| (known.colour[i] - entry.colour[i]).abs() <= verify::TOLERANCE)
+            })
+            .map(|known| ((known.hash ^ entry.hash).count_ones(), known))
+            .filter(|(distance, _)| *distance <= verify::THRESHOLD)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, known)| known.id.clone());
+
+        Some(Stack {
+            item,
+            count: count(slot).unwrap_or(1),
+        })
+    }
+
+    // kept as a picture of the slot, so that it is known from now on
+    pub fn learn(&mut self, id: &str, slot: &RgbImage) -> Result<()> {
+        if id.is_empty() || id.contains(['.', '/', '\\']) {
+            bail!("`{id}` is not an item ID");
+        }
+
+        let dir = self.dir.join(Self::SLOTS);
+        std::fs::create_dir_all(&dir)?;
+
+        let path = (1..)
+            .map(|i| match i {
+                1 => dir.join(format!("{id}.png")),
+                i => dir.join(format!("{id}.{i}.png")),
+            })
+            .find(|path| !path.exists())
+            .unwrap_or_default();
+
+        slot.save(path)?;
+        self.entries
+            .push(Entry::new(id.to_string(), &slot_top(slot)));
+        self.revision += 1;
+
+        Ok(())
+    }
+}
+
+impl Entry {
+    fn new(id: String, top: &RgbImage) -> Self {
+        Self {
+            id,
+            hash: verify::hash(top),
+            colour: verify::colour(top),
+        }
+    }
+}
+
+// the whole slot on a picture of the inventory with the hotbar, at the scale of the picture
+pub fn crop(picture: &RgbImage, grid: Grid, container: Container) -> Option<RgbImage> {
+    let (width, height) = picture.dimensions();
+    let centre = grid.picture(
+        container,
+        Picture::Thumbnail,
+        Coord(width as i32, height as i32),
+    )?;
+    // the inventory is 9 slots wide
+    let size = (width / 9) as i32;
+    let Coord(x, y) = centre.sub(Coord(size / 2, size / 2));
+
+    Some(imageops::crop_imm(picture, x as u32, y as u32, size as u32, size as u32).to_image())
+}
+
+// what the slots a recipe takes from hold on its thumbnail, totalled by item in order. unknown
+// items may differ, so each is listed on its own.
+pub fn ingredients(library: &Library, thumbnail: &RgbImage, recipe: &Recipe) -> Vec<Stack> {
+    let mut stacks: Vec<Stack> = Vec::new();
+
+    for grid in recipe.inputs() {
+        let Some(stack) =
+            crop(thumbnail, grid, recipe.container).and_then(|slot| library.classify(&slot))
+        else {
+            continue;
+        };
+
+        match stacks
+            .iter_mut()
+            .find(|known| stack.item.is_some() && known.item == stack.item)
+        {
+            Some(known) => known.count += stack.count,
+            None => stacks.push(stack),
+        }
+    }
+
+    stacks
+}
+
+// e.g. `oak_log` for `oak_log.2.png`
+fn id(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    let stem = name.strip_suffix(".png")?;
+
+    stem.split('.').next().map(str::to_string)
+}
+
+// the icon above the stack size, which is drawn over its bottom half
+fn slot_top(slot: &RgbImage) -> RgbImage {
+    let scale = (slot.width() / 18).max(1);
+
+    imageops::crop_imm(slot, scale, scale, 16 * scale, 9 * scale).to_image()
+}
+
+fn texture_top(texture: &RgbaImage) -> RgbImage {
+    let scale = (texture.width() / 16).max(1);
+    let mut top = RgbImage::from_pixel(16 * scale, 9 * scale, BACKGROUND);
+
+    for (x, y, pixel) in top.enumerate_pixels_mut() {
+        let Some(&image::Rgba([r, g, b, a])) = texture.get_pixel_checked(x, y) else {
+            continue;
+        };
+
+        let blend = |c: u8, background: u8| {
+            ((u32::from(c) * u32::from(a) + u32::from(background) * u32::from(255 - a)) / 255) as u8
+        };
+        *pixel = Rgb([blend(r, pixel[0]), blend(g, pixel[1]), blend(b, pixel[2])]);
+    }
+
+    top
+}
+
+// the white digits at the bottom-right, which are left out for a single item
+fn count(slot: &RgbImage) -> Option<u32> {
+    let scale = (slot.width() / 18).max(1);
+    let is_white = |x: u32, y: u32| {
+        slot.get_pixel_checked(x * scale + scale / 2, y * scale + scale / 2)
+            .is_some_and(|pixel| pixel.0.iter().all(|&c| 230 < c))
+    };
+
+    let mut count = None;
+    let mut place = 1;
+
+    // right-aligned, 6 pixels apart, in rows 10 to 16
+    for i in 1..=2 {
+        let left = 18 - 6 * i;
+        let bits: Vec<bool> = (10..17)
+            .flat_map(|y| (left..left + 5).map(move |x| (x, y)))
+            .map(|(x, y)| is_white(x, y))
+            .collect();
+
+        let (digit, distance) = DIGITS
+            .iter()
+            .enumerate()
+            .map(|(digit, rows)| {
+                let distance = rows
+                    .iter()
+                    .flat_map(|row| row.chars().map(|c| c == '#'))
+                    .zip(&bits)
+                    .filter(|(expected, actual)| expected != *actual)
+                    .count();
+                (digit as u32, distance)
+            })
+            .min_by_key(|(_, distance)| *distance)?;
+
+        if 3 < distance {
+            break;
+        }
+
+        count = Some(count.unwrap_or(0) + digit * place);
+        place *= 10;
+    }
+
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recipe::{Profile, Step};
+    use tempfile::TempDir;
+
+    fn fixture() -> Result<RgbImage> {
+        let path = format!("{}/fixtures/thumbnail.png", env!("CARGO_MANIFEST_DIR"));
+        Ok(image::open(path)?.into_rgb8())
+    }
+
+    fn slot(grid: Grid) -> Result<RgbImage> {
+        Ok(crop(&fixture()?, grid, Container::CraftingTable).unwrap())
+    }
+
+    #[test]
+    fn reads_stack_sizes() -> Result<()> {
+        assert_eq!(count(&slot(Grid::Inv(0, 1))?), Some(64));
+        assert_eq!(count(&slot(Grid::Hotbar(8))?), Some(64));
+
+        let mut single = slot(Grid::Inv(0, 1))?;
+        // covers the digits
+        for (x, y, pixel) in single.enumerate_pixels_mut() {
+            if 27 < x && 27 < y {
+                *pixel = BACKGROUND;
+            }
+        }
+        assert_eq!(count(&single), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn learns_items_and_lists_ingredients() -> Result<()> {
+        let dir = TempDir::new()?;
+        let mut library = Library::load(dir.path());
+
+        assert_eq!(
+            library.classify(&slot(Grid::Inv(0, 1))?).unwrap().item,
+            None
+        );
+
+        library.learn("iron_ingot", &slot(Grid::Inv(0, 1))?)?;
+        library.learn("redstone", &slot(Grid::Inv(4, 1))?)?;
+        library.learn("cobblestone", &slot(Grid::Inv(0, 2))?)?;
+        library.learn("oak_planks", &slot(Grid::Hotbar(6))?)?;
+        assert!(library.learn("../up", &slot(Grid::Hotbar(6))?).is_err());
+
+        // from the files, on other slots holding the same items
+        let library = Library::load(dir.path());
+        let stack = |item: &str, count| Stack {
+            item: Some(item.to_string()),
+            count,
+        };
+        let unknown = |count| Stack { item: None, count };
+
+        assert_eq!(
+            library.classify(&slot(Grid::Inv(3, 1))?),
+            Some(stack("iron_ingot", 64))
+        );
+        assert_eq!(
+            library.classify(&slot(Grid::Hotbar(2))?),
+            Some(stack("cobblestone", 64))
+        );
+
+        let recipe = Recipe::new(
+            Container::CraftingTable,
+            Profile::default(),
+            vec![
+                Step::Left(Grid::Inv(1, 1)),
+                Step::Left(Grid::Table(0, 0)),
+                Step::Left(Grid::Inv(5, 1)),
+                Step::Left(Grid::Table(1, 0)),
+                Step::Left(Grid::Inv(6, 1)),
+                Step::Left(Grid::Table(2, 0)),
+                Step::Left(Grid::Hotbar(8)),
+                Step::Left(Grid::Table(0, 1)),
+            ],
+        );
+
+        assert_eq!(
+            ingredients(&Library::default(), &fixture()?, &recipe),
+            [unknown(64), unknown(64), unknown(64), unknown(64)]
+        );
+        assert_eq!(
+            ingredients(&library, &fixture()?, &recipe),
+            [
+                stack("iron_ingot", 64),
+                stack("redstone", 128),
+                stack("oak_planks", 64)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reads_textures() -> Result<()> {
+        let dir = TempDir::new()?;
+        let textures = dir.path().join(Library::TEXTURES);
+        std::fs::create_dir(&textures)?;
+
+        // a red square in the middle of a transparent texture
+        let mut texture = RgbaImage::new(16, 16);
+        for (x, y, pixel) in texture.enumerate_pixels_mut() {
+            if (4..12).contains(&x) && (3..13).contains(&y) {
+                *pixel = image::Rgba([200, 20, 20, 255]);
+            }
+        }
+        texture.save(textures.join("red_square.png"))?;
+        // skipped without the rest
+        std::fs::write(textures.join("broken.png"), "not a picture")?;
+
+        // as drawn at a scale of 3
+        let mut slot = RgbImage::from_pixel(54, 54, BACKGROUND);
+        for (x, y, pixel) in slot.enumerate_pixels_mut() {
+            if (3 + 12..3 + 36).contains(&x) && (3 + 9..3 + 39).contains(&y) {
+                *pixel = Rgb([200, 20, 20]);
+            }
+        }
+
+        let library = Library::load(dir.path());
+        assert_eq!(library.skipped().len(), 1);
+        assert_eq!(
+            library.classify(&slot),
+            Some(Stack {
+                item: Some("red_square".to_string()),
+                count: 1
+            })
+        );
+
+        Ok(())
+    }
+}
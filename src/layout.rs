@@ -0,0 +1,116 @@
+use crate::coord::Coord;
+
+// where Minecraft puts a container: the GUI is scaled by an integer factor and the container is
+// centred on the scaled screen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    scale: i32,
+    // the size of the screen in GUI units
+    scaled: Coord,
+    offset: Coord,
+    container: Coord,
+}
+
+impl Layout {
+    pub const AUTO: u8 = 0;
+    pub const MAX_GUI_SCALE: u8 = 4;
+    // the crafting table, the furnace, the villager and most others
+    pub const CONTAINER: Coord = Coord(176, 166);
+    const MIN_SCALED: Coord = Coord(320, 240);
+
+    // the scale is lowered until the scaled screen fits `MIN_SCALED`, even when fixed
+    pub fn new((width, height): (i32, i32), gui_scale: u8) -> Self {
+        let limit = match gui_scale {
+            Self::AUTO => i32::MAX,
+            fixed => fixed.into(),
+        };
+
+        let mut scale = 1;
+        while scale < limit
+            && Self::MIN_SCALED.0 <= width / (scale + 1)
+            && Self::MIN_SCALED.1 <= height / (scale + 1)
+        {
+            scale += 1;
+        }
+
+        Self {
+            scale,
+            scaled: Coord(ceil_div(width, scale), ceil_div(height, scale)),
+            offset: Coord(0, 0),
+            container: Self::CONTAINER,
+        }
+    }
+
+    // for a window whose client area starts at `origin` on the screen
+    pub const fn offset(self, origin: Coord) -> Self {
+        Self {
+            offset: self.offset.add(origin),
+            ..self
+        }
+    }
+
+    // for a container of another size, in GUI units
+    pub const fn container(self, size: Coord) -> Self {
+        Self {
+            container: size,
+            ..self
+        }
+    }
+
+    pub const fn scale(&self) -> i32 {
+        self.scale
+    }
+
+    // GUI units relative to the container to screen pixels
+    pub const fn screen(&self, gui: Coord) -> Coord {
+        let origin = self.scaled.sub(self.container).div(2);
+        self.offset.add(origin.add(gui).mul(self.scale))
+    }
+}
+
+const fn ceil_div(lhs: i32, rhs: i32) -> i32 {
+    (lhs + rhs - 1) / rhs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_large_gui_at_full_hd() {
+        let layout = Layout::new((1920, 1080), 3);
+
+        assert_eq!(layout.scale(), 3);
+        assert_eq!(layout.screen(Coord(29, 16)), Coord(783, 339));
+        assert_eq!(layout.screen(Coord(7, 141)), Coord(717, 714));
+    }
+
+    #[test]
+    fn auto_picks_the_largest_scale_that_fits() {
+        assert_eq!(Layout::new((1920, 1080), Layout::AUTO).scale(), 4);
+        assert_eq!(Layout::new((1366, 768), Layout::AUTO).scale(), 3);
+        assert_eq!(Layout::new((854, 480), Layout::AUTO).scale(), 2);
+        assert_eq!(Layout::new((1366, 768), 4).scale(), 3);
+        assert_eq!(Layout::new((1920, 1080), 1).scale(), 1);
+    }
+
+    #[test]
+    fn follows_the_window() {
+        let layout = Layout::new((1920, 1080), 3).offset(Coord(1920, 40));
+        assert_eq!(layout.screen(Coord(29, 16)), Coord(1920 + 783, 40 + 339));
+    }
+
+    #[test]
+    fn centres_taller_containers() {
+        // a large chest is 222 units tall
+        let layout = Layout::new((1920, 1080), 3).container(Coord(176, 222));
+        assert_eq!(layout.screen(Coord(0, 0)), Coord(696, 207));
+    }
+
+    #[test]
+    fn centres_on_odd_sizes() {
+        // 1366 / 3 is rounded up to 456
+        let layout = Layout::new((1366, 768), 3);
+        assert_eq!(layout.screen(Coord(0, 0)), Coord(420, 135));
+    }
+}
@@ -1,20 +1,78 @@
 #![windows_subsystem = "windows"]
 
+mod cli;
+mod clock;
+mod config;
 mod coord;
 mod engine;
 mod grid;
 mod io;
+mod item;
+mod layout;
 mod map_err_anyhow;
+mod playlist;
+mod recipe;
 mod resources;
 mod state;
+mod verify;
 
 fn detail() -> anyhow::Result<()> {
-    use {engine::Engine, resources::Resources, state::State};
+    use {
+        cli::{Args, Command},
+        clock::SystemClock,
+        config::Config,
+        engine::Engine,
+        resources::{Resources, Thumbnails},
+        state::State,
+    };
+
+    let args = Args::parse(std::env::args().skip(1))?;
+    let config = Config::load(&args)?;
+
+    if let Some(Command::Migrate(dir)) = args.command {
+        let dir = dir.unwrap_or(config.recipes);
+        let migration = io::migrate(&dir)?;
+        let skipped = match migration.skipped.as_slice() {
+            [] => String::new(),
+            skipped => {
+                let names: Vec<_> = skipped
+                    .iter()
+                    .filter_map(|dir| dir.file_name())
+                    .map(|name| name.to_string_lossy())
+                    .collect();
+
+                format!(
+                    " Skipped {} whose {} already exists: {}.",
+                    skipped.len(),
+                    io::FILENAME_BACKUP,
+                    names.join(", ")
+                )
+            }
+        };
+
+        return io::message_box(
+            format!(
+                "Upgraded {} recipe(s) in {}, {} already up to date. The originals are kept as {}.{skipped}",
+                migration.upgraded,
+                dir.display(),
+                migration.current,
+                io::FILENAME_BACKUP,
+            ),
+            "Migrated recipes",
+        );
+    }
+
+    std::fs::create_dir_all(&config.recipes)?;
 
     let mut engine = Engine::new()?;
     let resources = Resources::new(engine.tex_creator())?;
-    let mut state = State::new(&resources)?;
-    let fonts = resources.load_fonts()?;
+    let mut state = State::new(
+        io::default_backend(engine.desktop_size()?)?,
+        Box::new(SystemClock),
+        &config,
+    )?;
+    let mut thumbnails = Thumbnails::default();
+    let fonts = resources.load_fonts(&config.font)?;
 
     engine.start_text_input();
 
@@ -33,8 +91,17 @@ fn detail() -> anyhow::Result<()> {
             }
         }
 
-        state.step(&resources)?;
-        engine.draw(&state, &fonts)?;
+        state.step()?;
+
+        if state.draw_required() || !engine.frame_initialized() {
+            thumbnails.update(
+                &resources,
+                state.recipes.get_path()?.map(AsRef::as_ref),
+                state.library(),
+            )?;
+        }
+
+        engine.draw(&state, &fonts, thumbnails.textures())?;
         Engine::sleep();
     }
 
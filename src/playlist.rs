@@ -0,0 +1,115 @@
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::{fmt, io::Read};
+
+// recipes played one after another, each a number of times
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Playlist {
+    pub stages: Box<[Stage]>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Stage {
+    // the name of a directory of the recipes
+    pub recipe: String,
+    #[serde(default = "Stage::once")]
+    pub repeat: u32,
+}
+
+impl Playlist {
+    pub fn from_reader(reader: impl Read) -> Result<Self> {
+        let playlist: Self = serde_json::from_reader(reader)?;
+        playlist.validate()?;
+
+        Ok(playlist)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.stages.is_empty() {
+            bail!("`stages`: must not be empty");
+        }
+
+        for (i, stage) in self.stages.iter().enumerate() {
+            // joined to the directory of the recipes, which it must not leave
+            if stage.recipe.is_empty()
+                || stage.recipe.contains(['/', '\\'])
+                || stage.recipe == "."
+                || stage.recipe == ".."
+            {
+                bail!("`stages[{i}].recipe`: must be the name of a recipe, not a path");
+            }
+
+            if stage.repeat == 0 {
+                bail!("`stages[{i}].repeat`: must be greater than 0");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Stage {
+    const fn once() -> u32 {
+        1
+    }
+}
+
+// e.g. "planks x4 > sticks"
+impl fmt::Display for Playlist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, stage) in self.stages.iter().enumerate() {
+            if i != 0 {
+                f.write_str(" > ")?;
+            }
+
+            match stage.repeat {
+                1 => write!(f, "{}", stage.recipe)?,
+                repeat => write!(f, "{} x{repeat}", stage.recipe)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_stages_once_by_default() -> Result<()> {
+        let playlist = Playlist::from_reader(
+            r#"{"stages":[{"recipe":"planks","repeat":4},{"recipe":"sticks"}]}"#.as_bytes(),
+        )?;
+
+        assert_eq!(playlist.stages[1].repeat, 1);
+        assert_eq!(playlist.to_string(), "planks x4 > sticks");
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_empty_stages_and_counts() {
+        let message = |json: &str| {
+            Playlist::from_reader(json.as_bytes())
+                .err()
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(message(r#"{"stages":[]}"#), "`stages`: must not be empty");
+        assert_eq!(
+            message(r#"{"stages":[{"recipe":"a"},{"recipe":"b","repeat":0}]}"#),
+            "`stages[1].repeat`: must be greater than 0"
+        );
+
+        for recipe in ["../planks", "tools/planks", "..\\\\planks", "..", ""] {
+            assert_eq!(
+                message(&format!(r#"{{"stages":[{{"recipe":"{recipe}"}}]}}"#)),
+                "`stages[0].recipe`: must be the name of a recipe, not a path"
+            );
+        }
+    }
+}
@@ -0,0 +1,481 @@
+use crate::{
+    config::millis,
+    coord::Coord,
+    grid::{Container, Grid},
+};
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    io::{Read, Write},
+    time::{Duration, SystemTime},
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Recipe {
+    pub version: u32,
+    // in seconds since the Unix epoch
+    pub created: u64,
+    pub container: Container,
+    // where the slots were when recorded, unknown for the recipes of version 0
+    pub profile: Option<Profile>,
+    // between the inputs of the steps, `intervals.play` of the config if not set
+    #[serde(
+        default,
+        with = "millis::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub interval: Option<Duration>,
+    pub steps: Box<[Entry]>,
+    #[serde(default)]
+    pub notes: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub resolution: (i32, i32),
+    pub gui_scale: u8,
+    // whether the calibration was used instead of the layout of the game
+    pub calibrated: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Entry {
+    pub step: Step,
+    // waited for before the step, in milliseconds in the file
+    #[serde(default, with = "millis", skip_serializing_if = "Duration::is_zero")]
+    pub delay: Duration,
+    // overrides that of the recipe
+    #[serde(
+        default,
+        with = "millis::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub interval: Option<Duration>,
+    // where the cursor went before the step, if recorded with the timing
+    #[serde(default, skip_serializing_if = "<[_]>::is_empty")]
+    pub path: Box<[Waypoint]>,
+}
+
+// the cursor in the client area of the game, `at` after the previous step in milliseconds in the file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Waypoint {
+    #[serde(with = "millis")]
+    pub at: Duration,
+    pub to: Coord,
+}
+
+// what is done to the slots, as the game would see it with the default controls
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Step {
+    Left(Grid),
+    Right(Grid),
+    ShiftLeft(Grid),
+    Middle(Grid),
+    // with the number key (1 to 9) of a hotbar slot
+    Swap(Grid, u8),
+    Drop(Grid),
+    // spreads the held items over the slots in this order
+    DragLeft(Box<[Grid]>),
+    DragRight(Box<[Grid]>),
+    // takes a stack of the item, by its ID in `item::Library`, from wherever it is in the inventory
+    // or the hotbar and puts it into a slot of the crafting grid
+    Fetch(String, Grid),
+}
+
+// a step repeated `count` times in a row, numbered from 0, on one of the slots it touches
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mark {
+    pub grid: Grid,
+    pub first: usize,
+    pub count: usize,
+}
+
+impl Recipe {
+    pub const VERSION: u32 = 1;
+
+    pub fn new(container: Container, profile: Profile, steps: Vec<Step>) -> Self {
+        Self {
+            version: Self::VERSION,
+            created: Self::seconds(SystemTime::now()),
+            container,
+            profile: Some(profile),
+            interval: None,
+            steps: steps.into_iter().map(Entry::from).collect(),
+            notes: String::new(),
+        }
+    }
+
+    pub fn steps(&self) -> impl Iterator<Item = &Step> {
+        self.steps.iter().map(|entry| &entry.step)
+    }
+
+    pub fn into_steps(self) -> impl Iterator<Item = Step> {
+        self.steps.into_iter().map(|entry| entry.step)
+    }
+
+    // older versions are upgraded, with `created` in place of the date they lack
+    pub fn from_reader(reader: impl Read, created: SystemTime) -> Result<Self> {
+        Ok(Self::upgrade(serde_json::from_reader(reader)?, created)?.0)
+    }
+
+    // also tells whether `value` was of an older version
+    pub fn upgrade(value: Value, created: SystemTime) -> Result<(Self, bool)> {
+        let version = match value.get("version") {
+            None => 0,
+            Some(version) => version
+                .as_u64()
+                .ok_or_else(|| anyhow!("`version`: not a number"))?,
+        };
+
+        let recipe: Self = match version {
+            // a bare list of left clicks in a crafting table
+            0 => {
+                let clicks: Vec<Grid> = serde_json::from_value(value)?;
+
+                Self {
+                    version: Self::VERSION,
+                    created: Self::seconds(created),
+                    container: Container::CraftingTable,
+                    profile: None,
+                    interval: None,
+                    steps: clicks
+                        .into_iter()
+                        .map(Step::Left)
+                        .map(Entry::from)
+                        .collect(),
+                    notes: String::new(),
+                }
+            }
+            1 => serde_json::from_value(value)?,
+            version => bail!("version {version} is newer than this program supports"),
+        };
+
+        recipe.validate()?;
+
+        Ok((recipe, version < u64::from(Self::VERSION)))
+    }
+
+    // the slots of the inventory, the hotbar or a chest which are taken from before anything is
+    // put into them, following roughly whether the cursor holds items
+    pub fn inputs(&self) -> Vec<Grid> {
+        let is_storage =
+            |grid: &Grid| matches!(grid, Grid::Inv(..) | Grid::Hotbar(_) | Grid::Chest(..));
+        let mut inputs = Vec::new();
+        let mut touched = Vec::new();
+        let mut is_held = false;
+
+        for step in self.steps() {
+            let (grid, is_taken) = match step {
+                Step::Left(grid) => {
+                    is_held ^= true;
+                    (*grid, is_held)
+                }
+                Step::Right(grid) => {
+                    let is_taken = !is_held;
+                    is_held = true;
+                    (*grid, is_taken)
+                }
+                Step::ShiftLeft(grid) => (*grid, true),
+                // the hotbar slot is what is put into a slot of the container
+                Step::Swap(grid, number) if !is_storage(grid) => (Grid::Hotbar(number - 1), true),
+                Step::Swap(grid, _) => (*grid, true),
+                Step::Middle(grid) | Step::Drop(grid) => (*grid, false),
+                Step::DragLeft(slots) | Step::DragRight(slots) => {
+                    touched.extend(slots.iter().copied());
+                    is_held = false;
+                    continue;
+                }
+                // found on the screen instead
+                Step::Fetch(..) => continue,
+            };
+
+            if is_taken && is_storage(&grid) && !touched.contains(&grid) {
+                inputs.push(grid);
+            }
+
+            touched.push(grid);
+        }
+
+        inputs
+    }
+
+    pub fn marks(&self) -> Vec<Mark> {
+        let steps: Vec<_> = self.steps().collect();
+        let mut marks = Vec::new();
+        let mut first = 0;
+
+        for run in steps.chunk_by(|a, b| a == b) {
+            marks.extend(run[0].slots().iter().map(|grid| Mark {
+                grid: *grid,
+                first,
+                count: run.len(),
+            }));
+            first += run.len();
+        }
+
+        marks
+    }
+
+    fn seconds(time: SystemTime) -> u64 {
+        time.duration_since(SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default()
+    }
+
+    fn validate(&self) -> Result<()> {
+        for step in self.steps() {
+            if let Step::Swap(_, number) = step
+                && !(1..=9).contains(number)
+            {
+                bail!("hotbar slot {number} does not exist");
+            }
+
+            if let Step::Fetch(_, grid) = step
+                && !(matches!(grid, Grid::Table(..)) && self.container.has(*grid))
+            {
+                bail!(
+                    "{grid:?} is not a slot of the crafting grid of {:?} to fetch into",
+                    self.container
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn to_writer(&self, writer: impl Write) -> Result<()> {
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+}
+
+impl Step {
+    pub fn slots(&self) -> &[Grid] {
+        match self {
+            Self::Left(grid)
+            | Self::Right(grid)
+            | Self::ShiftLeft(grid)
+            | Self::Middle(grid)
+            | Self::Swap(grid, _)
+            | Self::Drop(grid)
+            | Self::Fetch(_, grid) => std::slice::from_ref(grid),
+            Self::DragLeft(slots) | Self::DragRight(slots) => slots,
+        }
+    }
+
+    // unless it is a drag, which has a slot of its own for each one passed over, or a fetch out of
+    // the crafting grid
+    pub const fn retarget(&mut self, to: Grid) -> bool {
+        match self {
+            Self::Left(grid)
+            | Self::Right(grid)
+            | Self::ShiftLeft(grid)
+            | Self::Middle(grid)
+            | Self::Swap(grid, _)
+            | Self::Drop(grid) => {
+                *grid = to;
+                true
+            }
+            Self::Fetch(_, grid) if matches!(to, Grid::Table(..)) => {
+                *grid = to;
+                true
+            }
+            Self::DragLeft(_) | Self::DragRight(_) | Self::Fetch(..) => false,
+        }
+    }
+}
+
+impl From<Step> for Entry {
+    fn from(step: Step) -> Self {
+        Self {
+            step,
+            delay: Duration::ZERO,
+            interval: None,
+            path: Box::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(json: &str) -> Result<Recipe> {
+        Recipe::from_reader(
+            json.as_bytes(),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(42),
+        )
+    }
+
+    #[test]
+    fn upgrades_version_0() -> Result<()> {
+        let recipe = read(r#"[{"Table":[0,1]},"Craft"]"#)?;
+
+        assert_eq!(recipe.version, Recipe::VERSION);
+        assert_eq!(recipe.created, 42);
+        assert_eq!(recipe.container, Container::CraftingTable);
+        assert_eq!(recipe.profile, None);
+        assert_eq!(
+            recipe.steps().collect::<Vec<_>>(),
+            [&Step::Left(Grid::Table(0, 1)), &Step::Left(Grid::Craft)]
+        );
+
+        assert!(read(r#"{"container":"Furnace","clicks":["Fuel"]}"#).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn reads_version_1_without_paths() -> Result<()> {
+        let (recipe, upgraded) = Recipe::upgrade(
+            serde_json::json!({
+                "version": 1,
+                "created": 42,
+                "container": "Anvil",
+                "profile": null,
+                "steps": [{ "step": { "Left": { "Input": 0 } }, "delay": 10 }],
+            }),
+            SystemTime::now(),
+        )?;
+
+        assert!(!upgraded);
+        assert_eq!(recipe.steps[0].delay, Duration::from_millis(10));
+        assert!(recipe.steps[0].path.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn round_trips_steps() -> Result<()> {
+        let mut recipe = Recipe::new(
+            Container::Furnace,
+            Profile {
+                resolution: (1920, 1080),
+                gui_scale: 3,
+                calibrated: false,
+            },
+            vec![
+                Step::ShiftLeft(Grid::Input(0)),
+                Step::Swap(Grid::Fuel, 4),
+                Step::DragRight(Box::new([Grid::Inv(0, 0), Grid::Inv(1, 0)])),
+            ],
+        );
+        recipe.interval = Some(Duration::from_millis(20));
+        recipe.steps[1].delay = Duration::from_millis(250);
+        recipe.steps[2].interval = Some(Duration::from_millis(40));
+        recipe.steps[2].path = Box::new([Waypoint {
+            at: Duration::from_millis(15),
+            to: Coord(300, 200),
+        }]);
+        recipe.notes = "needs coal".to_string();
+
+        let mut json = Vec::new();
+        recipe.to_writer(&mut json)?;
+
+        let (read, upgraded) = Recipe::upgrade(serde_json::from_slice(&json)?, SystemTime::now())?;
+        assert_eq!(read, recipe);
+        assert!(!upgraded);
+
+        Ok(())
+    }
+
+    #[test]
+    fn finds_the_slots_taken_from() {
+        let recipe = Recipe::new(
+            Container::CraftingTable,
+            Profile::default(),
+            vec![
+                Step::Left(Grid::Inv(0, 0)),
+                Step::DragRight(Box::new([Grid::Table(0, 0), Grid::Table(1, 0)])),
+                Step::Left(Grid::Inv(0, 0)),
+                // puts the rest away
+                Step::Left(Grid::Inv(4, 0)),
+                Step::Right(Grid::Chest(2, 0)),
+                Step::Left(Grid::Table(2, 0)),
+                Step::Swap(Grid::Table(0, 1), 3),
+                Step::ShiftLeft(Grid::Craft),
+                Step::Fetch("stick".to_string(), Grid::Table(1, 1)),
+                Step::Left(Grid::Inv(4, 0)),
+            ],
+        );
+
+        assert_eq!(
+            recipe.inputs(),
+            [Grid::Inv(0, 0), Grid::Chest(2, 0), Grid::Hotbar(2)]
+        );
+    }
+
+    #[test]
+    fn marks_runs_of_steps_on_their_slots() {
+        let recipe = Recipe::new(
+            Container::CraftingTable,
+            Profile::default(),
+            vec![
+                Step::Left(Grid::Inv(0, 0)),
+                Step::Right(Grid::Table(1, 1)),
+                Step::Right(Grid::Table(1, 1)),
+                Step::DragLeft(Box::new([Grid::Table(0, 0), Grid::Table(1, 0)])),
+            ],
+        );
+        let mark = |grid, first, count| Mark { grid, first, count };
+
+        assert_eq!(
+            recipe.marks(),
+            [
+                mark(Grid::Inv(0, 0), 0, 1),
+                mark(Grid::Table(1, 1), 1, 2),
+                mark(Grid::Table(0, 0), 3, 1),
+                mark(Grid::Table(1, 0), 3, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_and_newer() {
+        let message = |json: &str| read(json).err().unwrap().to_string();
+        let steps = |container, steps| {
+            format!(
+                r#"{{"version":1,"created":0,"container":"{container}","profile":null,"steps":{steps}}}"#
+            )
+        };
+
+        assert_eq!(
+            message(&steps("Furnace", r#"[{"step":{"Swap":["Fuel",0]}}]"#)),
+            "hotbar slot 0 does not exist"
+        );
+        assert_eq!(
+            message(&steps("Furnace", r#"[{"step":{"Fetch":["coal","Fuel"]}}]"#)),
+            "Fuel is not a slot of the crafting grid of Furnace to fetch into"
+        );
+        assert_eq!(
+            message(&steps(
+                "Anvil",
+                r#"[{"step":{"Fetch":["coal",{"Table":[0,0]}]}}]"#
+            )),
+            "Table(0, 0) is not a slot of the crafting grid of Anvil to fetch into"
+        );
+        assert_eq!(
+            message(&steps(
+                "Inventory",
+                r#"[{"step":{"Fetch":["coal",{"Table":[2,0]}]}}]"#
+            )),
+            "Table(2, 0) is not a slot of the crafting grid of Inventory to fetch into"
+        );
+        assert!(
+            read(&steps(
+                "Inventory",
+                r#"[{"step":{"Fetch":["coal",{"Table":[1,1]}]}}]"#
+            ))
+            .is_ok()
+        );
+        assert_eq!(
+            message(r#"{"version":3}"#),
+            "version 3 is newer than this program supports"
+        );
+    }
+}
@@ -1,4 +1,9 @@
-use crate::{io, map_err_anyhow::MapErrAnyhow};
+use crate::{
+    io,
+    item::{self, Library, Stack},
+    map_err_anyhow::MapErrAnyhow,
+    recipe::Recipe,
+};
 use anyhow::Result;
 use sdl2::{
     image::LoadTexture,
@@ -6,7 +11,10 @@ use sdl2::{
     ttf::{Font, Sdl2TtfContext},
     video::WindowContext,
 };
-use std::path::Path;
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
 pub struct Resources {
     ttf: Sdl2TtfContext,
@@ -21,10 +29,21 @@ pub struct Fonts<'resources> {
 pub struct Textures<'resources> {
     pub thumbnail: Texture<'resources>,
     pub item: Texture<'resources>,
+    // for the steps drawn over the thumbnail, unless it cannot be read
+    pub recipe: Option<Recipe>,
+    // in the slots it takes from on the thumbnail
+    pub ingredients: Box<[Stack]>,
+}
+
+// textures of the selected recipe, reloaded only when the recipe (its directory, or its steps once
+// they are edited) or the items known to the library change
+#[derive(Default)]
+pub struct Thumbnails<'resources> {
+    source: Option<(PathBuf, [Option<SystemTime>; 2], usize)>,
+    textures: Option<Textures<'resources>>,
 }
 
 impl Resources {
-    const FONT: &str = "CascadiaMono.ttf";
     const REGULAR: u16 = 16;
     const LARGE: u16 = 22;
 
@@ -34,19 +53,30 @@ impl Resources {
         Ok(Self { ttf, tex_creator })
     }
 
-    pub fn load_fonts(&self) -> Result<Fonts<'_>> {
+    pub fn load_fonts(&self, path: impl AsRef<Path>) -> Result<Fonts<'_>> {
+        let path = path.as_ref();
+
         Ok(Fonts {
-            regular: self.load_font(Self::FONT, Self::REGULAR)?,
-            large: self.load_font(Self::FONT, Self::LARGE)?,
+            regular: self.load_font(path, Self::REGULAR)?,
+            large: self.load_font(path, Self::LARGE)?,
         })
     }
 
-    pub fn load_textures(&self, path: impl AsRef<Path>) -> Result<Textures<'_>> {
+    pub fn load_textures(&self, path: impl AsRef<Path>, library: &Library) -> Result<Textures<'_>> {
         let path = path.as_ref();
+        let recipe = io::load_recipe(path.join(io::FILENAME_CLICKS)).ok();
+        let ingredients = match (&recipe, image::open(path.join(io::FILENAME_THUMBNAIL))) {
+            (Some(recipe), Ok(thumbnail)) => {
+                item::ingredients(library, &thumbnail.into_rgb8(), recipe).into()
+            }
+            _ => Box::default(),
+        };
 
         Ok(Textures {
             thumbnail: self.load_texture(path.join(io::FILENAME_THUMBNAIL))?,
             item: self.load_texture(path.join(io::FILENAME_ITEM))?,
+            recipe,
+            ingredients,
         })
     }
 
@@ -61,3 +91,32 @@ impl Resources {
         self.ttf.load_font(path, point_size).map_err_anyhow()
     }
 }
+
+impl<'resources> Thumbnails<'resources> {
+    pub fn update(
+        &mut self,
+        resources: &'resources Resources,
+        path: Option<&Path>,
+        library: &Library,
+    ) -> Result<()> {
+        let source = path.map(|path| {
+            let modified = [path.to_path_buf(), path.join(io::FILENAME_CLICKS)]
+                .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok());
+            (path.to_path_buf(), modified, library.revision())
+        });
+
+        if source != self.source {
+            self.textures = match &source {
+                None => None,
+                Some((path, ..)) => Some(resources.load_textures(path, library)?),
+            };
+            self.source = source;
+        }
+
+        Ok(())
+    }
+
+    pub const fn textures(&self) -> Option<&Textures<'resources>> {
+        self.textures.as_ref()
+    }
+}
@@ -1,78 +1,147 @@
 pub mod detail;
-mod key;
+pub mod key;
 mod recipes;
 pub mod spam;
+#[cfg(test)]
+mod tests;
 
 use crate::{
+    clock::Clock,
+    config::{Calibration, Config, Game, Intervals, Playback},
     coord::Coord,
-    grid::Grid,
-    io,
-    resources::Resources,
-    state::{
-        detail::{Cursor, Detail, TradeFirst, TradeSecond},
-        recipes::Recipes,
+    grid::{self, Container, Geometry, Grid},
+    io::{self, Frames, InputBackend, MouseButton, VirtualKey},
+    item::{self, Library},
+    layout::Layout,
+    playlist::Playlist,
+    recipe::{Profile, Recipe, Step},
+    state::detail::{
+        Detail, Drag, DryRun, Op, Progress, Repeat, Schedule, Timed, Timing, TradeFirst,
+        TradeSecond,
     },
+    verify,
 };
-use anyhow::Result;
-use key::{Key, Keys};
+use anyhow::{Result, bail};
+use image::RgbImage;
+use key::{Bindings, Capture, Chord, Key, Keys};
+pub use recipes::Recipes;
 use spam::Spam;
 use std::{
-    path::Path,
+    path::{Path, PathBuf},
     time::{Duration, Instant},
 };
-use windows::Win32::UI::Input::KeyboardAndMouse as kam;
 
-pub struct State<'resources> {
+pub struct State {
+    backend: Box<dyn InputBackend>,
+    clock: Box<dyn Clock>,
+    intervals: Intervals,
+    game: Game,
+    playback: Playback,
+    calibration: Option<Calibration>,
+    container: Container,
+    // an index into `SPEEDS`
+    speed: usize,
+    // the client area of the game as of the last `update_window`
+    client: (Coord, (i32, i32)),
+    layout: Layout,
+    geometry: Geometry,
+    screenshots: PathBuf,
+    library: Library,
+    // of the screenshot directory, where it can be watched
+    watcher: Option<Box<dyn io::Watcher>>,
     detail: Detail,
     draw_required: bool,
+    config_path: Option<PathBuf>,
+    bindings: Bindings,
     keys: Keys,
     pub spam_left: Spam,
     pub spam_right: Spam,
     pub spam_space: Spam,
-    pub recipes: Recipes<'resources>,
+    pub recipes: Recipes,
     double_click_active: bool,
     double_click_origin: Option<Instant>,
     is_locked: bool,
 }
 
-impl<'resources> State<'resources> {
-    const INT_LEFT: Duration = Duration::from_millis(10);
-    const INT_RIGHT: Duration = Duration::from_millis(10);
-    const INT_SPACE: Duration = Duration::from_millis(50);
-    const INT_PLAY: Duration = Duration::from_millis(7);
-    const INT_DOUBLE_CLICK: Duration = Duration::from_millis(50);
-    const SCREENSHOTS: &'static str =
-        r"C:\Users\Suika\AppData\Roaming\.minecraft\versions\1.8.9-OptiFine_HD_U_M5\screenshots";
-
-    pub fn new(resources: &'resources Resources) -> Result<Self> {
-        use io::MouseButton;
+impl State {
+    // centres of the first input and the output slot of the villager GUI, in GUI units
+    const TRADE_INPUT: Coord = Coord(44, 61);
+    const TRADE_OUTPUT: Coord = Coord(128, 61);
+    const INT_VALIDATE: Duration = Duration::from_millis(100);
+    const INT_PREVIEW: Duration = Duration::from_millis(500);
+    // for the game to draw the slots after the last input of a run, before they are looked at
+    const INT_SETTLE: Duration = Duration::from_millis(50);
+    // multipliers of the playback speed, which divide the intervals and the delays
+    const SPEEDS: [f64; 7] = [0.25, 0.5, 0.75, 1.0, 1.5, 2.0, 4.0];
+    const SPEED_NORMAL: usize = 3;
+
+    pub fn new(
+        backend: Box<dyn InputBackend>,
+        clock: Box<dyn Clock>,
+        config: &Config,
+    ) -> Result<Self> {
+        let now = clock.now();
+        let intervals = config.intervals;
 
         let spam_left = Spam::new(
-            State::INT_LEFT,
-            || io::send_mouse_down(MouseButton::Left),
-            || io::send_mouse_up(MouseButton::Left),
+            intervals.left,
+            now,
+            |backend| backend.send_mouse_down(MouseButton::Left),
+            |backend| backend.send_mouse_up(MouseButton::Left),
         );
 
         let spam_right = Spam::new(
-            State::INT_RIGHT,
-            || io::send_mouse_down(MouseButton::Right),
-            || io::send_mouse_up(MouseButton::Right),
+            intervals.right,
+            now,
+            |backend| backend.send_mouse_down(MouseButton::Right),
+            |backend| backend.send_mouse_up(MouseButton::Right),
         );
 
         let spam_space = Spam::new(
-            State::INT_SPACE,
-            || io::send_key_down(kam::VK_SPACE),
-            || io::send_key_up(kam::VK_SPACE),
+            intervals.space,
+            now,
+            |backend| backend.send_key_down(VirtualKey::Space),
+            |backend| backend.send_key_up(VirtualKey::Space),
         );
 
+        // the rest of the items are still known
+        let library = Library::load(&config.items);
+
+        if !library.skipped().is_empty() {
+            backend.message_box(
+                &format!("Reason: {}", library.skipped().join("\n")),
+                "Failed to load items",
+            )?;
+        }
+
         Ok(Self {
+            backend,
+            clock,
+            intervals,
+            game: config.game.clone(),
+            playback: config.playback,
+            calibration: config.calibration.clone(),
+            container: Container::default(),
+            speed: Self::SPEED_NORMAL,
+            client: (Coord(0, 0), config.game.resolution),
+            layout: Layout::new(config.game.resolution, config.game.gui_scale),
+            geometry: Geometry::new(
+                &Layout::new(config.game.resolution, config.game.gui_scale),
+                Container::default(),
+            ),
+            screenshots: config.screenshots.clone(),
+            library,
+            // e.g. the directory does not exist yet, which saving will complain about
+            watcher: io::watcher(&config.screenshots).ok().flatten(),
             detail: Detail::Idle,
             draw_required: false,
-            keys: Keys::new(),
+            config_path: config.path.clone(),
+            bindings: config.keys.clone(),
+            keys: Keys::new(&config.keys),
             spam_left,
             spam_right,
             spam_space,
-            recipes: Recipes::new(resources)?,
+            recipes: Recipes::new(&config.recipes)?,
             double_click_active: false,
             double_click_origin: None,
             is_locked: false,
@@ -87,8 +156,35 @@ impl<'resources> State<'resources> {
         &self.detail
     }
 
+    pub const fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+
+    pub const fn library(&self) -> &Library {
+        &self.library
+    }
+
+    pub const fn container(&self) -> Container {
+        self.container
+    }
+
+    pub const fn speed(&self) -> f64 {
+        Self::SPEEDS[self.speed]
+    }
+
     pub const fn is_locked(&self) -> bool {
-        self.is_locked || matches!(self.detail, Detail::Naming { .. } | Detail::Renaming { .. })
+        self.is_locked
+            || matches!(
+                self.detail,
+                Detail::Naming { .. }
+                    | Detail::Renaming { .. }
+                    | Detail::Learning { .. }
+                    | Detail::Repeating { .. }
+                    | Detail::Rebinding {
+                        capture: Some(_),
+                        ..
+                    }
+            )
     }
 
     pub fn double_click_active(&self) -> bool {
@@ -108,6 +204,15 @@ impl<'resources> State<'resources> {
         | Detail::Renaming {
             name,
             draw_required,
+        }
+        | Detail::Learning {
+            name,
+            draw_required,
+            ..
+        }
+        | Detail::Repeating {
+            count: name,
+            draw_required,
         } = &mut self.detail
         {
             *name += text;
@@ -124,6 +229,15 @@ impl<'resources> State<'resources> {
         | Detail::Renaming {
             name,
             draw_required,
+        }
+        | Detail::Learning {
+            name,
+            draw_required,
+            ..
+        }
+        | Detail::Repeating {
+            count: name,
+            draw_required,
         } = &mut self.detail
         {
             name.pop();
@@ -131,24 +245,66 @@ impl<'resources> State<'resources> {
         }
     }
 
-    pub fn step(&mut self, resources: &'resources Resources) -> Result<()> {
+    pub fn step(&mut self) -> Result<()> {
         self.draw_required = false;
+        self.backend.update()?;
         self.update_keys();
         self.toggle_spams();
-        self.on_step();
+        self.on_step()?;
+        self.watch_screenshots()?;
 
         self.detail = match std::mem::take(&mut self.detail) {
-            Detail::Idle => self.on_idle(resources),
-            Detail::Recording { clicks, count } => self.on_record(clicks, count),
+            Detail::Idle => self.on_idle(),
+            Detail::Recording {
+                steps,
+                count,
+                drag,
+                timing,
+                frames,
+            } => self.on_record(steps, count, drag, timing, frames),
             Detail::Naming {
-                clicks,
+                steps,
+                timing,
+                frames,
                 name,
                 draw_required,
             } => {
                 self.draw_required |= draw_required;
-                self.on_name(clicks, name, resources)
+                self.on_name(steps, timing, frames, name)
             }
-            Detail::Playing { clicks, origin } => self.on_play(clicks, origin),
+            Detail::Playing {
+                stages,
+                stage,
+                progress,
+                dry_run,
+            } => self.on_play(stages, stage, progress, dry_run),
+            Detail::Mismatched {
+                stages,
+                dry_run,
+                slots,
+            } => self.on_mismatch(stages, dry_run, slots),
+            Detail::Repeating {
+                count,
+                draw_required,
+            } => {
+                self.draw_required |= draw_required;
+                self.on_repeat(count)
+            }
+            Detail::Choosing {
+                playlists,
+                selected,
+            } => self.on_choose(playlists, selected),
+            Detail::Editing {
+                dir,
+                recipe,
+                selected,
+                edited,
+            } => self.on_edit(dir, recipe, selected, edited),
+            Detail::Previewing {
+                recipe,
+                current,
+                origin,
+            } => self.on_preview(recipe, current, origin),
             Detail::TradingFirst {
                 state,
                 position,
@@ -159,26 +315,68 @@ impl<'resources> State<'resources> {
                 position,
                 origin,
             } => self.on_trade_second(state, position, origin),
-            Detail::Deleting => self.on_delete(resources),
+            Detail::Deleting => self.on_delete(),
             Detail::Renaming {
                 name,
                 draw_required,
             } => {
                 self.draw_required |= draw_required;
-                self.on_rename(name, resources)
+                self.on_rename(name)
             }
+            Detail::Learning {
+                slot,
+                name,
+                draw_required,
+            } => {
+                self.draw_required |= draw_required;
+                self.on_learn(slot, name)
+            }
+            Detail::Calibrating { points } => self.on_calibrate(points),
+            Detail::Validating {
+                slots,
+                current,
+                origin,
+            } => self.on_validate(slots, current, origin),
+            Detail::Rebinding {
+                selected,
+                capture,
+                conflict,
+            } => self.on_rebind(selected, capture, conflict),
         }?;
 
         Ok(())
     }
 
-    fn on_step(&mut self) {
+    // only the first two taken while recording or naming count, and the rest are ignored
+    fn watch_screenshots(&mut self) -> Result<()> {
+        let Some(watcher) = &self.watcher else {
+            return Ok(());
+        };
+
+        for path in watcher.poll()? {
+            if let Detail::Recording { frames, .. } | Detail::Naming { frames, .. } =
+                &mut self.detail
+                && let Some(screenshots) = &mut frames.screenshots
+                && screenshots.len() < 2
+            {
+                screenshots.push(path);
+                self.draw_required = true;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_step(&mut self) -> Result<()> {
         if self.keys.lock.is_pressed() {
             self.is_locked ^= true;
         }
 
         if self.keys.abort.is_pressed() {
-            self.detail = Detail::Idle;
+            // a dry run is written out as it stops
+            if let Detail::Playing { dry_run, .. } = std::mem::take(&mut self.detail) {
+                self.finish_dry_run(dry_run)?;
+            }
         }
 
         if self.keys.double_click.is_pressed() {
@@ -190,62 +388,156 @@ impl<'resources> State<'resources> {
         }
 
         if self.double_click_active() && self.keys.right_click.is_pressed() {
-            self.double_click_origin = Some(Instant::now());
+            self.double_click_origin = Some(self.clock.now());
         }
 
         if let Some(instant) = self.double_click_origin
-            && Self::INT_DOUBLE_CLICK <= instant.elapsed()
+            && self.intervals.double_click <= self.clock.now() - instant
         {
-            io::send_mouse(io::MouseButton::Right);
+            self.backend.send_mouse(MouseButton::Right)?;
             self.double_click_origin = None;
         }
 
-        let now = Instant::now();
+        let now = self.clock.now();
 
-        self.spam_left.step(now);
-        self.spam_right.step(now);
-        self.spam_space.step(now);
+        self.spam_left.step(now, self.backend.as_ref())?;
+        self.spam_right.step(now, self.backend.as_ref())?;
+        self.spam_space.step(now, self.backend.as_ref())
     }
 
-    fn on_idle(&mut self, resources: &'resources Resources) -> Result<Detail> {
+    fn on_idle(&mut self) -> Result<Detail> {
         if self.keys.prev.is_pressed() {
             if self.keys.prev_skip.is_pressed() {
-                self.recipes.decrement_skip(resources)?;
+                self.recipes.decrement_skip();
             } else {
-                self.recipes.decrement(resources)?;
+                self.recipes.decrement();
             }
         }
 
         if self.keys.next.is_pressed() {
             if self.keys.next_skip.is_pressed() {
-                self.recipes.increment_skip(resources)?;
+                self.recipes.increment_skip();
             } else {
-                self.recipes.increment(resources)?;
+                self.recipes.increment();
             }
         }
 
+        if self.keys.begin_trade.is_pressed()
+            || self.keys.record.is_pressed()
+            || self.keys.record_timed.is_pressed()
+            || self.keys.edit.is_pressed()
+            || self.keys.learn.is_pressed()
+            || self.keys.calibrate.is_pressed()
+        {
+            self.update_window()?;
+        }
+
+        if self.keys.speed_down.is_pressed() {
+            self.speed = self.speed.saturating_sub(1);
+        }
+
+        if self.keys.speed_up.is_pressed() {
+            self.speed = (self.speed + 1).min(Self::SPEEDS.len() - 1);
+        }
+
+        if self.keys.container.is_pressed() {
+            self.container = self.container.next();
+            self.update_window()?;
+        }
+
         let retval = if self.keys.begin_trade.is_pressed() {
-            io::send_mouse(io::MouseButton::Left);
+            self.backend.send_mouse(MouseButton::Left)?;
 
             Detail::TradingFirst {
                 state: TradeFirst::InvClicked,
-                position: io::get_cursor()?,
-                origin: Instant::now(),
+                position: self.backend.get_cursor()?,
+                origin: self.clock.now(),
             }
-        } else if self.keys.record.is_pressed() {
-            Detail::Recording {
-                clicks: Vec::new(),
-                count: 0,
+        } else if self.keys.record.is_pressed() || self.keys.record_timed.is_pressed() {
+            match self.grab_inventory() {
+                Ok(inventory) => Detail::Recording {
+                    steps: Vec::new(),
+                    count: 0,
+                    drag: None,
+                    timing: self.keys.record_timed.is_pressed().then(Timing::default),
+                    frames: Frames {
+                        inventory,
+                        item: None,
+                        after: None,
+                        screenshots: self.watcher.is_some().then(Vec::new),
+                    },
+                },
+                Err(e) => {
+                    self.backend
+                        .message_box(&format!("Reason: {e}"), "Failed to record recipe")?;
+
+                    Detail::Idle
+                }
+            }
+        } else if self.keys.play.is_pressed() {
+            self.play_selected(Repeat::Times(1), false)?
+        } else if self.keys.dry_run.is_pressed() {
+            self.play_selected(Repeat::Times(1), true)?
+        } else if self.keys.repeat.is_pressed() {
+            Detail::Repeating {
+                count: String::new(),
+                draw_required: false,
+            }
+        } else if self.keys.playlists.is_pressed() {
+            match io::playlists(self.recipes.dir()) {
+                Ok(playlists) => Detail::Choosing {
+                    playlists,
+                    selected: 0,
+                },
+                Err(e) => {
+                    self.backend
+                        .message_box(&format!("Reason: {e}"), "Failed to load playlists")?;
+                    Detail::Idle
+                }
             }
-        } else if self.keys.play.is_pressed()
-            && let Some(path) = self.recipes.get_path()?
+        } else if self.keys.edit.is_pressed()
+            && let Some(dir) = self.recipes.get_path()?.cloned()
         {
-            Detail::Playing {
-                clicks: io::load_clicks(path.join(io::FILENAME_CLICKS))?
-                    .into_iter()
-                    .map(|grid| (grid, Cursor::New))
-                    .collect(),
-                origin: Instant::now(),
+            match io::load_recipe(dir.join(io::FILENAME_CLICKS)) {
+                Ok(recipe) => Detail::Editing {
+                    dir,
+                    recipe,
+                    selected: 0,
+                    edited: false,
+                },
+                Err(e) => {
+                    self.backend
+                        .message_box(&format!("Reason: {e}"), "Failed to edit recipe")?;
+                    Detail::Idle
+                }
+            }
+        } else if self.keys.preview.is_pressed()
+            && let Some(dir) = self.recipes.get_path()?
+        {
+            match io::load_recipe(dir.join(io::FILENAME_CLICKS)) {
+                Ok(recipe) => Detail::Previewing {
+                    recipe,
+                    current: 0,
+                    origin: self.clock.now(),
+                },
+                Err(e) => {
+                    self.backend
+                        .message_box(&format!("Reason: {e}"), "Failed to preview recipe")?;
+                    Detail::Idle
+                }
+            }
+        } else if self.keys.learn.is_pressed() {
+            match self.grab_slot() {
+                Ok(slot) => Detail::Learning {
+                    slot,
+                    name: String::new(),
+                    draw_required: false,
+                },
+                Err(e) => {
+                    self.backend
+                        .message_box(&format!("Reason: {e}"), "Failed to learn item")?;
+                    Detail::Idle
+                }
             }
         } else if self.keys.delete.is_pressed() {
             Detail::Deleting
@@ -254,6 +546,14 @@ impl<'resources> State<'resources> {
                 name: String::new(),
                 draw_required: false,
             }
+        } else if self.keys.calibrate.is_pressed() {
+            Detail::Calibrating { points: Vec::new() }
+        } else if self.keys.rebind.is_pressed() {
+            Detail::Rebinding {
+                selected: 0,
+                capture: None,
+                conflict: None,
+            }
         } else {
             Detail::Idle
         };
@@ -261,60 +561,220 @@ impl<'resources> State<'resources> {
         Ok(retval)
     }
 
-    fn on_record(&mut self, mut clicks: Vec<Grid>, mut count: usize) -> Result<Detail> {
-        if self.keys.click.is_pressed() {
-            let coord = Coord::from(io::get_cursor()?);
+    fn on_record(
+        &mut self,
+        mut steps: Vec<Step>,
+        mut count: usize,
+        drag: Option<Drag>,
+        mut timing: Option<Timing>,
+        mut frames: Frames,
+    ) -> Result<Detail> {
+        if self.keys.undo.is_pressed() && drag.is_none() && steps.pop().is_some() {
+            count = Self::repeats(&steps);
+            timing.as_mut().map(Timing::undo);
+        }
 
-            if let Ok(grid) = coord.try_into() {
-                self.draw_required = true;
+        let cursor = Coord::from(self.backend.get_cursor()?);
+        let grid = Grid::at(cursor, &self.geometry);
+        let was_dragging = drag.is_some();
+        let is_shift_down =
+            self.backend.is_down(VirtualKey::LShift) || self.backend.is_down(VirtualKey::RShift);
+        let keys = &self.keys;
+
+        let (step, drag) = match (drag, grid) {
+            (Some(mut drag), grid) => {
+                if let Some(grid) = grid
+                    && !drag.slots.contains(&grid)
+                {
+                    drag.slots.push(grid);
+                }
 
-                count = match clicks.last() {
-                    Some(last) if last == &grid => count + 1,
-                    _ => 1,
+                let is_released = match drag.button {
+                    MouseButton::Left => keys.click.is_released(),
+                    _ => keys.right_click.is_released(),
+                };
+
+                match is_released {
+                    true => (Some(Self::dragged(drag)), None),
+                    false => (None, Some(drag)),
+                }
+            }
+            (None, Some(grid)) if keys.click.is_pressed() && is_shift_down => {
+                (Some(Step::ShiftLeft(grid)), None)
+            }
+            (None, Some(grid)) if keys.click.is_pressed() || keys.right_click.is_pressed() => {
+                let button = match keys.click.is_pressed() {
+                    true => MouseButton::Left,
+                    false => MouseButton::Right,
+                };
+                let drag = Drag {
+                    button,
+                    slots: vec![grid],
                 };
 
-                clicks.push(grid);
+                (None, Some(drag))
+            }
+            (None, Some(grid)) if keys.middle.is_pressed() => (Some(Step::Middle(grid)), None),
+            (None, Some(grid)) if keys.drop.is_pressed() => (Some(Step::Drop(grid)), None),
+            (None, Some(grid)) => {
+                let number = keys.hotbar.iter().position(Key::is_pressed);
+                (number.map(|i| Step::Swap(grid, i as u8 + 1)), None)
+            }
+            (None, None) => (None, None),
+        };
+
+        if let Some(timing) = &mut timing {
+            let now = self.clock.now();
+            let (origin, _) = self.client;
+
+            if !was_dragging && (drag.is_some() || step.is_some()) {
+                timing.begin(now, cursor.sub(origin));
+            } else {
+                timing.follow(now, cursor.sub(origin));
+            }
+
+            if step.is_some() {
+                timing.end();
             }
         }
 
-        let retval = if self.keys.record.is_pressed() {
+        if let Some(step) = step {
+            self.draw_required = true;
+
+            count = match steps.last() {
+                Some(last) if last == &step => count + 1,
+                _ => 1,
+            };
+
+            steps.push(step);
+        }
+
+        let is_stopped = self.keys.record.is_pressed() || self.keys.record_timed.is_pressed();
+        let grabbed = match is_stopped {
+            true => self
+                .grab_item()
+                .and_then(|item| Ok((item, self.grab_inventory()?))),
+            false => Ok((None, None)),
+        };
+
+        // still recording, so that stopping can be tried again
+        if let Err(e) = &grabbed {
+            self.backend
+                .message_box(&format!("Reason: {e}"), "Failed to record recipe")?;
+        }
+
+        let retval = if is_stopped && let Ok((item, after)) = grabbed {
+            frames.item = item;
+            frames.after = after;
+
             Detail::Naming {
-                clicks,
+                steps,
+                timing: timing.map(Timing::into_steps),
+                frames,
                 name: String::new(),
                 draw_required: false,
             }
         } else {
-            Detail::Recording { clicks, count }
+            Detail::Recording {
+                steps,
+                count,
+                drag,
+                timing,
+                frames,
+            }
         };
 
         Ok(retval)
     }
 
+    // the inventory with the hotbar, which is cropped from a screenshot if the backend cannot read
+    // the screen
+    fn grab_inventory(&self) -> Result<Option<RgbImage>> {
+        let Some((Coord(x, y), Coord(width, height))) = self.geometry.player() else {
+            return Ok(None);
+        };
+
+        self.backend.capture(x, y, width as u32, height as u32)
+    }
+
+    // the result slot, like the inventory
+    fn grab_item(&self) -> Result<Option<RgbImage>> {
+        let layout = self.layout.container(self.container.size());
+        let (origin, size) = self.container.item();
+        let Coord(x, y) = layout.screen(origin);
+        let Coord(width, height) = size.mul(layout.scale());
+
+        self.backend.capture(x, y, width as u32, height as u32)
+    }
+
+    // how many times the last step was taken in a row
+    fn repeats(steps: &[Step]) -> usize {
+        steps
+            .iter()
+            .rev()
+            .take_while(|step| Some(*step) == steps.last())
+            .count()
+    }
+
+    // a click unless the cursor left the slot before the button was released
+    fn dragged(Drag { button, mut slots }: Drag) -> Step {
+        match (button, slots.len()) {
+            (MouseButton::Left, 1) => Step::Left(slots.remove(0)),
+            (MouseButton::Left, _) => Step::DragLeft(slots.into()),
+            (_, 1) => Step::Right(slots.remove(0)),
+            (_, _) => Step::DragRight(slots.into()),
+        }
+    }
+
     fn on_name(
         &mut self,
-        clicks: Vec<Grid>,
+        steps: Vec<Step>,
+        timing: Option<Box<[Timed]>>,
+        frames: Frames,
         name: String,
-        resources: &'resources Resources,
     ) -> Result<Detail> {
         let retval = if self.keys.confirm.is_pressed() {
-            match io::save_clicks(Self::SCREENSHOTS, Recipes::RECIPES, &clicks, &name) {
+            let mut recipe = Recipe::new(self.container, self.profile(), steps);
+
+            // the pauses between the steps as recorded, while the inputs within a step keep the
+            // interval of the recipe
+            if let Some(timing) = &timing {
+                for (entry, timed) in recipe.steps.iter_mut().zip(timing) {
+                    entry.delay = timed.delay;
+                    entry.path = timed.path.clone();
+                }
+            }
+
+            match io::save_recipe(
+                &self.screenshots,
+                self.recipes.dir(),
+                &recipe,
+                &name,
+                self.game.gui_scale,
+                &frames,
+            ) {
                 Err(e) => {
-                    io::message_box(format!("Reason: {e}"), "Failed to crate recipe")?;
+                    self.backend
+                        .message_box(&format!("Reason: {e}"), "Failed to crate recipe")?;
 
                     Detail::Naming {
-                        clicks,
+                        steps: recipe.into_steps().collect(),
+                        timing,
+                        frames,
                         name,
                         draw_required: false,
                     }
                 }
                 Ok(_) => {
-                    self.reload_recipes(resources)?;
+                    self.reload_recipes()?;
                     Detail::Idle
                 }
             }
         } else {
             Detail::Naming {
-                clicks,
+                steps,
+                timing,
+                frames,
                 name,
                 draw_required: false,
             }
@@ -323,41 +783,585 @@ impl<'resources> State<'resources> {
         Ok(retval)
     }
 
-    fn on_play(&mut self, mut clicks: Box<[(Grid, Cursor)]>, origin: Instant) -> Result<Detail> {
-        let index = (origin.elapsed().as_secs_f64() / Self::INT_PLAY.as_secs_f64()) as usize;
+    fn play_selected(&mut self, repeat: Repeat, dry_run: bool) -> Result<Detail> {
+        match self.recipes.get_path()?.cloned() {
+            Some(path) => self.play(&[(path, repeat)], dry_run, self.playback.verify),
+            None => Ok(Detail::Idle),
+        }
+    }
+
+    // the recipes in these directories one after another, as a single playback
+    fn play(
+        &mut self,
+        stages: &[(PathBuf, Repeat)],
+        dry_run: bool,
+        verify: bool,
+    ) -> Result<Detail> {
+        self.update_window()?;
+
+        let mut schedules = Vec::new();
+
+        for (path, repeat) in stages {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            let mut recipe = match io::load_recipe(path.join(io::FILENAME_CLICKS)) {
+                Ok(recipe) => recipe,
+                Err(e) => return self.refuse_to_play(&e.to_string()),
+            };
+
+            // the slots of one container are somewhere else, or nowhere, in another
+            if recipe.container != self.container {
+                return self.refuse_to_play(&format!(
+                    "{name} is for {:?} but {:?} is selected",
+                    recipe.container, self.container
+                ));
+            }
 
-        let retval = match clicks.get_mut(index / 2) {
-            None => {
-                self.draw_required = true;
-                Detail::Idle
+            // the points of the paths are elsewhere in a window of another size
+            if recipe
+                .profile
+                .is_none_or(|profile| profile.resolution != self.client.1)
+            {
+                for entry in &mut recipe.steps {
+                    entry.path = Box::default();
+                }
+            }
+
+            schedules.push(Schedule::new(
+                name,
+                path.clone(),
+                recipe,
+                self.intervals.play,
+                self.speed(),
+                *repeat,
+            ));
+        }
+
+        if let Some(schedule) = schedules.first()
+            && let Some(grid) = self.empty_input(&schedule.inputs)?
+        {
+            return self.refuse_to_play(&format!("{grid:?} is empty"));
+        }
+
+        if verify && let Some(schedule) = schedules.first() {
+            let slots = match self
+                .mismatches(&schedule.dir.join(io::FILENAME_THUMBNAIL), &schedule.inputs)
+            {
+                Ok(slots) => slots,
+                Err(e) => return self.refuse_to_play(&e.to_string()),
+            };
+
+            if !slots.is_empty() {
+                return Ok(Detail::Mismatched {
+                    stages: stages.into(),
+                    dry_run,
+                    slots: slots.into(),
+                });
+            }
+        }
+
+        let origin = self.clock.now();
+        let dry_run = match dry_run {
+            false => None,
+            true => match DryRun::new(self.recipes.dir().join(io::FILENAME_DRY_RUN), origin) {
+                Ok(dry_run) => Some(dry_run),
+                Err(e) => return self.refuse_to_play(&e.to_string()),
+            },
+        };
+
+        Ok(Detail::Playing {
+            stages: schedules.into(),
+            stage: 0,
+            // looked at above
+            progress: Progress {
+                checked: true,
+                ..Progress::new(1, origin)
+            },
+            dry_run,
+        })
+    }
+
+    // recipes without the picture, or a screen which cannot be read, are not verified
+    fn mismatches(&self, picture: &Path, slots: &[Grid]) -> Result<Vec<Grid>> {
+        let Ok(picture) = image::open(picture) else {
+            return Ok(Vec::new());
+        };
+        let Some(inventory) = self.grab_inventory()? else {
+            return Ok(Vec::new());
+        };
+
+        Ok(verify::mismatches(
+            &picture.into_rgb8(),
+            &inventory,
+            self.container,
+            slots,
+        ))
+    }
+
+    // whether anything is compared once the stage is done, by `verify_stages`
+    fn is_verified(&self, stages: &[Schedule], stage: usize, dry_run: bool) -> bool {
+        let Some(done) = stages.get(stage) else {
+            return false;
+        };
+        let is_after =
+            done.repeat == Repeat::Times(1) && done.dir.join(io::FILENAME_AFTER).exists();
+        let is_next = stages
+            .get(stage + 1)
+            .is_some_and(|next| next.dir.join(io::FILENAME_THUMBNAIL).exists());
+
+        !dry_run && self.playback.verify && (is_after || is_next)
+    }
+
+    // the stage which is done is compared with what it left when it was recorded, if it was played
+    // once, and the next one with its thumbnail. a dry run leaves the screen as it was.
+    fn verify_stages(
+        &mut self,
+        stages: &[Schedule],
+        stage: usize,
+        dry_run: bool,
+    ) -> Result<Option<Detail>> {
+        if dry_run || !self.playback.verify {
+            return Ok(None);
+        }
+
+        if let Some(done) = stage.checked_sub(1).and_then(|done| stages.get(done))
+            && done.repeat == Repeat::Times(1)
+        {
+            let slots = match self.mismatches(&done.dir.join(io::FILENAME_AFTER), &done.inputs) {
+                Ok(slots) => slots,
+                Err(e) => return self.refuse_to_play(&e.to_string()).map(Some),
+            };
+
+            if !slots.is_empty() {
+                let slots: Vec<_> = slots.iter().map(|grid| format!("{grid:?}")).collect();
+
+                return self
+                    .refuse_to_play(&format!(
+                        "{} left {} unlike when it was recorded",
+                        done.name,
+                        slots.join(", ")
+                    ))
+                    .map(Some);
+            }
+        }
+
+        if let Some(next) = stages.get(stage) {
+            let slots = match self.mismatches(&next.dir.join(io::FILENAME_THUMBNAIL), &next.inputs)
+            {
+                Ok(slots) => slots,
+                Err(e) => return self.refuse_to_play(&e.to_string()).map(Some),
+            };
+
+            if !slots.is_empty() {
+                // so that they are marked on its thumbnail
+                self.recipes.select(&next.dir);
+
+                return Ok(Some(Detail::Mismatched {
+                    stages: stages[stage..]
+                        .iter()
+                        .map(|schedule| (schedule.dir.clone(), schedule.repeat))
+                        .collect(),
+                    dry_run,
+                    slots: slots.into(),
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // played anyway once confirmed
+    fn on_mismatch(
+        &mut self,
+        stages: Box<[(PathBuf, Repeat)]>,
+        dry_run: bool,
+        slots: Box<[Grid]>,
+    ) -> Result<Detail> {
+        if self.keys.confirm.is_pressed() {
+            return self.play(&stages, dry_run, false);
+        }
+
+        Ok(Detail::Mismatched {
+            stages,
+            dry_run,
+            slots,
+        })
+    }
+
+    fn refuse_to_play(&self, reason: &str) -> Result<Detail> {
+        self.backend
+            .message_box(&format!("Reason: {reason}"), "Failed to play recipe")?;
+
+        Ok(Detail::Idle)
+    }
+
+    fn on_play(
+        &mut self,
+        stages: Box<[Schedule]>,
+        stage: usize,
+        progress: Progress,
+        mut dry_run: Option<DryRun>,
+    ) -> Result<Detail> {
+        let detail = self.play_ops(stages, stage, progress, &mut dry_run);
+        // left here once playback stops, since the detail takes it back while it goes on
+        self.finish_dry_run(dry_run)?;
+
+        detail
+    }
+
+    fn finish_dry_run(&self, dry_run: Option<DryRun>) -> Result<()> {
+        if let Some(dry_run) = dry_run
+            && let Err(e) = dry_run.finish()
+        {
+            self.backend
+                .message_box(&format!("Reason: {e}"), "Failed to write dry run")?;
+        }
+
+        Ok(())
+    }
+
+    fn play_ops(
+        &mut self,
+        stages: Box<[Schedule]>,
+        mut stage: usize,
+        mut progress: Progress,
+        dry_run: &mut Option<DryRun>,
+    ) -> Result<Detail> {
+        // the next run may not have started yet
+        let elapsed = self.clock.now().checked_duration_since(progress.origin);
+
+        if !progress.checked && elapsed.is_some() {
+            if progress.run == 1
+                && 0 < stage
+                && let Some(detail) = self.verify_stages(&stages, stage, dry_run.is_some())?
+            {
+                return Ok(detail);
+            }
+
+            let Some(schedule) = stages.get(stage) else {
+                return Ok(Detail::Idle);
+            };
+
+            if self.empty_input(&schedule.inputs)?.is_some() {
+                return Ok(Detail::Idle);
             }
-            Some((grid, cursor)) => {
-                match cursor {
-                    Cursor::New if index & 1 == 0 => {
-                        grid.set_cursor()?;
-                        *cursor = Cursor::Moved;
+
+            progress.checked = true;
+        }
+
+        // the last stage is looked at once it is due, like the next one would be
+        let Some(schedule) = stages.get(stage) else {
+            return Ok(Detail::Playing {
+                stages,
+                stage,
+                progress,
+                dry_run: dry_run.take(),
+            });
+        };
+
+        while let Some((at, op)) = schedule.ops.get(progress.next)
+            && elapsed.is_some_and(|elapsed| *at <= elapsed)
+        {
+            let backend = self.backend.as_ref();
+            let found;
+            // the slot of a fetch is looked for when it is due, after the previous steps are done.
+            // a dry run goes on without it.
+            let op = match op {
+                Op::Find(item) => match self.find_stack(item, &progress.fetched) {
+                    Ok(Some(grid)) => {
+                        progress.fetched.push(grid);
+                        found = Op::Move(grid);
+                        &found
                     }
-                    Cursor::Moved if index & 1 == 1 => {
-                        io::send_mouse(io::MouseButton::Left);
-                        *cursor = Cursor::Clicked;
+                    _ if dry_run.is_some() => op,
+                    Ok(None) => {
+                        return self.refuse_to_play(&format!(
+                            "no `{item}` in the inventory or the hotbar"
+                        ));
                     }
-                    Cursor::New | Cursor::Moved | Cursor::Clicked => (),
+                    Err(e) => return self.refuse_to_play(&e.to_string()),
+                },
+                op => op,
+            };
+
+            if let Some(dry_run) = dry_run {
+                dry_run.log(progress.origin + *at, op, &self.geometry, self.client.0)?;
+                self.draw_required = true;
+                progress.next += 1;
+                continue;
+            }
+
+            match op {
+                Op::Move(grid) => grid.set_cursor(&self.geometry, backend)?,
+                Op::Find(_) => (),
+                Op::Point(point) => {
+                    let Coord(x, y) = self.client.0.add(*point);
+                    backend.set_cursor(x, y)?;
+                }
+                Op::Click(button) => backend.send_mouse(*button)?,
+                Op::Down(button) => backend.send_mouse_down(*button)?,
+                Op::Up(button) => backend.send_mouse_up(*button)?,
+                Op::KeyDown(vkey) => backend.send_key_down(*vkey)?,
+                Op::KeyUp(vkey) => backend.send_key_up(*vkey)?,
+                Op::Tap(vkey) => {
+                    backend.send_key_down(*vkey)?;
+                    backend.send_key_up(*vkey)?;
                 }
+            }
+
+            progress.next += 1;
+        }
 
-                Detail::Playing { clicks, origin }
+        if progress.next < schedule.ops.len() {
+            return Ok(Detail::Playing {
+                stages,
+                stage,
+                progress,
+                dry_run: dry_run.take(),
+            });
+        }
+
+        // the next run, of this stage or the next one, starts an interval after the last input
+        self.draw_required = true;
+        let mut origin = progress.origin + schedule.length;
+
+        let is_last = schedule.repeat.is_last(progress.run);
+        let is_verified = is_last && self.is_verified(&stages, stage, dry_run.is_some());
+
+        // the slot under the cursor would be highlighted, and the game needs a moment to draw them
+        if self.playback.stop_when_empty || is_verified {
+            if dry_run.is_none() {
+                let Coord(x, y) = self.client.0;
+                self.backend.set_cursor(x, y)?;
             }
+
+            origin += Self::INT_SETTLE;
+        }
+
+        let progress = if is_last {
+            stage += 1;
+            Progress::new(1, origin)
+        } else {
+            Progress::new(progress.run + 1, origin)
+        };
+
+        // what the last stage left is looked at too
+        let retval = match stages.get(stage) {
+            None if !is_verified => Detail::Idle,
+            _ => Detail::Playing {
+                stages,
+                stage,
+                progress,
+                dry_run: dry_run.take(),
+            },
         };
 
         Ok(retval)
     }
 
+    // an empty count repeats until aborted
+    fn on_repeat(&mut self, count: String) -> Result<Detail> {
+        if !self.keys.confirm.is_pressed() {
+            return Ok(Detail::Repeating {
+                count,
+                draw_required: false,
+            });
+        }
+
+        let repeat = match count.trim() {
+            "" => Repeat::UntilAbort,
+            count => match count.parse() {
+                Ok(times) if 0 < times => Repeat::Times(times),
+                _ => {
+                    self.backend.message_box(
+                        &format!("Reason: `{count}` is not a number of times"),
+                        "Failed to repeat recipe",
+                    )?;
+
+                    return Ok(Detail::Repeating {
+                        count: String::new(),
+                        draw_required: false,
+                    });
+                }
+            },
+        };
+
+        self.play_selected(repeat, false)
+    }
+
+    fn on_choose(
+        &mut self,
+        playlists: Box<[(String, Playlist)]>,
+        mut selected: usize,
+    ) -> Result<Detail> {
+        let count = playlists.len();
+
+        if self.keys.prev.is_pressed() && 0 < count {
+            selected = (selected + count - 1) % count;
+        }
+
+        if self.keys.next.is_pressed() && 0 < count {
+            selected = (selected + 1) % count;
+        }
+
+        if self.keys.playlists.is_pressed() {
+            return Ok(Detail::Idle);
+        }
+
+        match playlists.get(selected) {
+            Some((_, playlist)) if self.keys.confirm.is_pressed() => {
+                let stages: Vec<_> = playlist
+                    .stages
+                    .iter()
+                    .map(|stage| {
+                        (
+                            self.recipes.dir().join(&stage.recipe),
+                            Repeat::Times(stage.repeat),
+                        )
+                    })
+                    .collect();
+
+                self.play(&stages, false, self.playback.verify)
+            }
+            _ => Ok(Detail::Choosing {
+                playlists,
+                selected,
+            }),
+        }
+    }
+
+    // a click on a slot of the game moves the selected step there
+    fn on_edit(
+        &mut self,
+        dir: PathBuf,
+        mut recipe: Recipe,
+        mut selected: usize,
+        mut edited: bool,
+    ) -> Result<Detail> {
+        if self.keys.edit.is_pressed() {
+            return Ok(Detail::Idle);
+        }
+
+        let keys = &self.keys;
+        let mut steps = std::mem::take(&mut recipe.steps).into_vec();
+        let len = steps.len();
+        let before = (selected, edited);
+
+        if keys.prev.is_pressed() && 0 < len {
+            selected = (selected + len - 1) % len;
+        }
+
+        if keys.next.is_pressed() && 0 < len {
+            selected = (selected + 1) % len;
+        }
+
+        if keys.move_up.is_pressed() && 0 < selected && selected < len {
+            steps.swap(selected - 1, selected);
+            selected -= 1;
+            edited = true;
+        }
+
+        if keys.move_down.is_pressed() && selected + 1 < len {
+            steps.swap(selected, selected + 1);
+            selected += 1;
+            edited = true;
+        }
+
+        if keys.duplicate.is_pressed() && selected < len {
+            steps.insert(selected + 1, steps[selected].clone());
+            selected += 1;
+            edited = true;
+        }
+
+        if keys.delete.is_pressed() && selected < len {
+            steps.remove(selected);
+            selected = selected.min(steps.len().saturating_sub(1));
+            edited = true;
+        }
+
+        if keys.click.is_pressed()
+            && let Some(entry) = steps.get_mut(selected)
+            && let Some(grid) = Grid::at(
+                Coord::from(self.backend.get_cursor()?),
+                &self.geometry_of(recipe.container),
+            )
+        {
+            edited |= entry.step.retarget(grid);
+        }
+
+        recipe.steps = steps.into();
+
+        if self.keys.confirm.is_pressed() {
+            match io::update_recipe(&dir, &recipe) {
+                Ok(()) => edited = false,
+                Err(e) => self
+                    .backend
+                    .message_box(&format!("Reason: {e}"), "Failed to save recipe")?,
+            }
+        }
+
+        self.draw_required |= before != (selected, edited);
+
+        Ok(Detail::Editing {
+            dir,
+            recipe,
+            selected,
+            edited,
+        })
+    }
+
+    // like validation, but nothing is sent to the game
+    fn on_preview(&mut self, recipe: Recipe, current: usize, origin: Instant) -> Result<Detail> {
+        let interval = Self::INT_PREVIEW.div_f64(self.speed());
+        let index = ((self.clock.now() - origin).as_secs_f64() / interval.as_secs_f64()) as usize;
+
+        if self.keys.preview.is_pressed() || recipe.steps.len() <= index {
+            self.draw_required = true;
+            return Ok(Detail::Idle);
+        }
+
+        self.draw_required |= current != index;
+
+        Ok(Detail::Previewing {
+            recipe,
+            current: index,
+            origin,
+        })
+    }
+
+    // the first of `inputs` which looks empty on the screen, if checking is enabled and the screen
+    // can be read
+    fn empty_input(&self, inputs: &[Grid]) -> Result<Option<Grid>> {
+        if !self.playback.stop_when_empty {
+            return Ok(None);
+        }
+
+        for grid in inputs {
+            let Some((Coord(x, y), Coord(width, height))) = grid.inner(&self.geometry) else {
+                continue;
+            };
+
+            if let Some(image) = self.backend.capture(x, y, width as u32, height as u32)?
+                && grid::is_empty(&image)
+            {
+                return Ok(Some(*grid));
+            }
+        }
+
+        Ok(None)
+    }
+
     fn on_trade_first(
         &mut self,
         state: TradeFirst,
         position: (i32, i32),
         origin: Instant,
     ) -> Result<Detail> {
-        let index = (origin.elapsed().as_secs_f64() / Self::INT_PLAY.as_secs_f64()) as usize;
+        let index = ((self.clock.now() - origin).as_secs_f64() / self.intervals.play.as_secs_f64())
+            as usize;
 
         let f = |state| Detail::TradingFirst {
             state,
@@ -367,24 +1371,24 @@ impl<'resources> State<'resources> {
 
         let retval = match state {
             TradeFirst::InvClicked if 0 < index => {
-                io::set_cursor(828, 474)?;
+                self.set_cursor(Self::TRADE_INPUT)?;
                 f(TradeFirst::MovedToLeft)
             }
             TradeFirst::MovedToLeft if 1 < index => {
-                io::send_mouse(io::MouseButton::Left);
+                self.backend.send_mouse(MouseButton::Left)?;
                 f(TradeFirst::LeftClicked)
             }
             TradeFirst::LeftClicked if 2 < index => {
-                io::set_cursor(1080, 474)?;
+                self.set_cursor(Self::TRADE_OUTPUT)?;
                 f(TradeFirst::Waiting)
             }
             TradeFirst::Waiting if self.keys.end_trade.is_pressed() => {
-                io::send_mouse(io::MouseButton::Left);
+                self.backend.send_mouse(MouseButton::Left)?;
 
                 Detail::TradingSecond {
                     state: TradeSecond::RightClicked,
                     position,
-                    origin: Instant::now(),
+                    origin: self.clock.now(),
                 }
             }
             TradeFirst::InvClicked
@@ -402,7 +1406,8 @@ impl<'resources> State<'resources> {
         position: (i32, i32),
         origin: Instant,
     ) -> Result<Detail> {
-        let index = (origin.elapsed().as_secs_f64() / Self::INT_PLAY.as_secs_f64()) as usize;
+        let index = ((self.clock.now() - origin).as_secs_f64() / self.intervals.play.as_secs_f64())
+            as usize;
 
         let f = |state| Detail::TradingSecond {
             state,
@@ -412,15 +1417,15 @@ impl<'resources> State<'resources> {
 
         let retval = match state {
             TradeSecond::RightClicked if 0 < index => {
-                io::set_cursor(828, 474)?;
+                self.set_cursor(Self::TRADE_INPUT)?;
                 f(TradeSecond::MovedToLeft)
             }
             TradeSecond::MovedToLeft if 1 < index => {
-                io::send_mouse(io::MouseButton::Left);
+                self.backend.send_mouse(MouseButton::Left)?;
                 f(TradeSecond::LeftClicked)
             }
             TradeSecond::LeftClicked if 2 < index => {
-                io::set_cursor(position.0, position.1)?;
+                self.backend.set_cursor(position.0, position.1)?;
                 self.draw_required = true;
                 Detail::Idle
             }
@@ -432,11 +1437,12 @@ impl<'resources> State<'resources> {
         Ok(retval)
     }
 
-    fn on_delete(&mut self, resources: &'resources Resources) -> Result<Detail> {
+    fn on_delete(&mut self) -> Result<Detail> {
         let retval = if self.keys.confirm.is_pressed() {
-            match self.recipes.delete(resources) {
+            match self.recipes.delete() {
                 Err(e) => {
-                    io::message_box(format!("Reason: {e}"), "Failed to delete recipe")?;
+                    self.backend
+                        .message_box(&format!("Reason: {e}"), "Failed to delete recipe")?;
                     Detail::Deleting
                 }
                 Ok(_) => Detail::Idle,
@@ -448,14 +1454,12 @@ impl<'resources> State<'resources> {
         Ok(retval)
     }
 
-    fn on_rename(&mut self, name: String, resources: &'resources Resources) -> Result<Detail> {
+    fn on_rename(&mut self, name: String) -> Result<Detail> {
         let retval = if self.keys.confirm.is_pressed() {
-            match self
-                .recipes
-                .rename(Path::new(Recipes::RECIPES).join(&name), resources)
-            {
+            match self.recipes.rename(self.recipes.dir().join(&name)) {
                 Err(e) => {
-                    io::message_box(format!("Reason: {e}"), "Failed to rename recipe")?;
+                    self.backend
+                        .message_box(&format!("Reason: {e}"), "Failed to rename recipe")?;
                     Detail::Renaming {
                         name,
                         draw_required: false,
@@ -473,27 +1477,287 @@ impl<'resources> State<'resources> {
         Ok(retval)
     }
 
+    fn on_learn(&mut self, slot: RgbImage, name: String) -> Result<Detail> {
+        if self.keys.confirm.is_pressed() {
+            match self.library.learn(name.trim(), &slot) {
+                Ok(()) => return Ok(Detail::Idle),
+                Err(e) => self
+                    .backend
+                    .message_box(&format!("Reason: {e}"), "Failed to learn item")?,
+            }
+        }
+
+        Ok(Detail::Learning {
+            slot,
+            name,
+            draw_required: false,
+        })
+    }
+
+    // the whole slot under the cursor, unless it is empty
+    fn grab_slot(&self) -> Result<RgbImage> {
+        let cursor = Coord::from(self.backend.get_cursor()?);
+        let Some(grid) = Grid::at(cursor, &self.geometry) else {
+            bail!("the cursor is not on a slot");
+        };
+
+        let Some(slot) = self.capture_slot(grid)? else {
+            bail!("the screen cannot be read");
+        };
+
+        if self.library.classify(&slot).is_none() {
+            bail!("the slot is empty");
+        }
+
+        Ok(slot)
+    }
+
+    // the whole slot, if it is in this container and the screen can be read
+    fn capture_slot(&self, grid: Grid) -> Result<Option<RgbImage>> {
+        let Some(Coord(x, y)) = grid
+            .coord(&self.geometry)
+            .map(|centre| centre.sub(self.geometry.slot.div(2)))
+        else {
+            return Ok(None);
+        };
+        let Coord(width, height) = self.geometry.slot;
+
+        self.backend.capture(x, y, width as u32, height as u32)
+    }
+
+    // the first slot of the inventory or the hotbar which holds `item`, other than `except`, on one
+    // capture of the screen
+    fn find_stack(&self, item: &str, except: &[Grid]) -> Result<Option<Grid>> {
+        let Some((Coord(x, y), Coord(width, height))) = self.geometry.player() else {
+            return Ok(None);
+        };
+        let Some(inventory) = self.backend.capture(x, y, width as u32, height as u32)? else {
+            bail!("the screen cannot be read to find `{item}`");
+        };
+
+        let found = self
+            .geometry
+            .slots()
+            .filter(|grid| {
+                matches!(grid, Grid::Inv(..) | Grid::Hotbar(_)) && !except.contains(grid)
+            })
+            .find(|grid| {
+                item::crop(&inventory, *grid, self.container)
+                    .and_then(|slot| self.library.classify(&slot))
+                    .is_some_and(|stack| stack.item.as_deref() == Some(item))
+            });
+
+        Ok(found)
+    }
+
+    fn on_calibrate(&mut self, mut points: Vec<Coord>) -> Result<Detail> {
+        if self.keys.click.is_pressed() {
+            let (origin, _) = self.client;
+            points.push(Coord::from(self.backend.get_cursor()?).sub(origin));
+            self.draw_required = true;
+        }
+
+        if points.len() < Geometry::CALIBRATION.len() {
+            return Ok(Detail::Calibrating { points });
+        }
+
+        let geometry = match Geometry::calibrate(&points) {
+            Ok(geometry) => geometry,
+            Err(e) => {
+                self.backend
+                    .message_box(&format!("Reason: {e}"), "Failed to calibrate")?;
+                return Ok(Detail::Idle);
+            }
+        };
+
+        let (origin, resolution) = self.client;
+        let calibration = Calibration {
+            resolution,
+            geometry,
+        };
+
+        // the calibration is of the crafting table
+        self.container = Container::CraftingTable;
+        self.geometry = calibration.geometry.offset(origin);
+        self.calibration = Some(calibration.clone());
+
+        if let Some(path) = &self.config_path
+            && let Err(e) = Config::update_file(path, |config| {
+                config.calibration = Some(calibration);
+            })
+        {
+            self.backend
+                .message_box(&format!("Reason: {e}"), "Failed to save calibration")?;
+        }
+
+        Ok(Detail::Validating {
+            slots: self.geometry.slots().collect(),
+            current: None,
+            origin: self.clock.now(),
+        })
+    }
+
+    // moves the cursor through every slot so that misplaced ones can be spotted
+    fn on_validate(
+        &mut self,
+        slots: Box<[Grid]>,
+        current: Option<usize>,
+        origin: Instant,
+    ) -> Result<Detail> {
+        let index =
+            ((self.clock.now() - origin).as_secs_f64() / Self::INT_VALIDATE.as_secs_f64()) as usize;
+
+        let retval = match slots.get(index) {
+            None => {
+                self.draw_required = true;
+                Detail::Idle
+            }
+            Some(grid) => {
+                if current != Some(index) {
+                    grid.set_cursor(&self.geometry, self.backend.as_ref())?;
+                    self.draw_required = true;
+                }
+
+                Detail::Validating {
+                    slots,
+                    current: Some(index),
+                    origin,
+                }
+            }
+        };
+
+        Ok(retval)
+    }
+
+    fn on_rebind(
+        &mut self,
+        mut selected: usize,
+        capture: Option<Capture>,
+        mut conflict: Option<Chord>,
+    ) -> Result<Detail> {
+        let backend = self.backend.as_ref();
+
+        let retval = match capture {
+            Some(mut capture) => {
+                let held = capture.held().len();
+
+                match capture.update(backend) {
+                    Some(chord) => {
+                        self.draw_required = true;
+                        conflict = self.rebind(selected, chord)?;
+
+                        Detail::Rebinding {
+                            selected,
+                            capture: None,
+                            conflict,
+                        }
+                    }
+                    None => {
+                        self.draw_required |= held != capture.held().len();
+
+                        Detail::Rebinding {
+                            selected,
+                            capture: Some(capture),
+                            conflict,
+                        }
+                    }
+                }
+            }
+            None => {
+                let count = self.bindings.iter().count();
+
+                if self.keys.prev.is_pressed() {
+                    selected = (selected + count - 1) % count;
+                    conflict = None;
+                }
+
+                if self.keys.next.is_pressed() {
+                    selected = (selected + 1) % count;
+                    conflict = None;
+                }
+
+                if self.keys.confirm.is_pressed() {
+                    Detail::Rebinding {
+                        selected,
+                        capture: Some(Capture::new(backend)),
+                        conflict: None,
+                    }
+                } else if self.keys.rebind.is_pressed() {
+                    Detail::Idle
+                } else {
+                    Detail::Rebinding {
+                        selected,
+                        capture: None,
+                        conflict,
+                    }
+                }
+            }
+        };
+
+        Ok(retval)
+    }
+
+    // returns the chord back if another action is bound to it
+    fn rebind(&mut self, index: usize, chord: Chord) -> Result<Option<Chord>> {
+        let Some((name, _)) = self.bindings.iter().nth(index) else {
+            return Ok(None);
+        };
+
+        if self.bindings.conflict(name, &chord).is_some() {
+            return Ok(Some(chord));
+        }
+
+        if let Some((_, bound)) = self.bindings.iter_mut().nth(index) {
+            *bound = chord;
+        }
+
+        self.keys = Keys::new(&self.bindings);
+
+        if let Some(path) = &self.config_path
+            && let Err(e) = Config::update_file(path, |config| config.keys = self.bindings.clone())
+        {
+            self.backend
+                .message_box(&format!("Reason: {e}"), "Failed to save key bindings")?;
+        }
+
+        Ok(None)
+    }
+
     fn update_keys(&mut self) {
         // does not count as a modification which needs redraw
-        self.keys.click.update(false);
-        self.keys.right_click.update(false);
+        let backend = self.backend.as_ref();
+        self.keys.click.update(false, backend);
+        self.keys.right_click.update(false, backend);
+        self.keys.middle.update(false, backend);
+        self.keys.drop.update(false, backend);
+        for key in &mut self.keys.hotbar {
+            key.update(false, backend);
+        }
 
-        let mut update_nolock = |key: &mut Key| {
-            key.update(false);
+        // so that chords with them can be captured too
+        let is_capturing = matches!(
+            self.detail,
+            Detail::Rebinding {
+                capture: Some(_),
+                ..
+            }
+        );
+        let mut update_nolock = |key: &mut Key, is_disabled: bool| {
+            key.update(is_disabled, backend);
 
             if key.is_pressed() {
                 self.draw_required = true;
             }
         };
 
-        update_nolock(&mut self.keys.abort);
-        update_nolock(&mut self.keys.lock);
-        update_nolock(&mut self.keys.cancel_dc);
-        update_nolock(&mut self.keys.confirm);
+        update_nolock(&mut self.keys.abort, is_capturing);
+        update_nolock(&mut self.keys.lock, is_capturing);
+        update_nolock(&mut self.keys.cancel_dc, false);
+        update_nolock(&mut self.keys.confirm, false);
 
         let is_locked = self.is_locked();
         let mut update = |key: &mut Key| {
-            key.update(is_locked);
+            key.update(is_locked, backend);
 
             if key.is_pressed() {
                 self.draw_required = true;
@@ -504,6 +1768,15 @@ impl<'resources> State<'resources> {
         update(&mut self.keys.right);
         update(&mut self.keys.space);
         update(&mut self.keys.record);
+        update(&mut self.keys.record_timed);
+        update(&mut self.keys.undo);
+        update(&mut self.keys.edit);
+        update(&mut self.keys.duplicate);
+        update(&mut self.keys.move_up);
+        update(&mut self.keys.move_down);
+        update(&mut self.keys.preview);
+        update(&mut self.keys.dry_run);
+        update(&mut self.keys.learn);
         update(&mut self.keys.play);
         update(&mut self.keys.prev);
         update(&mut self.keys.next);
@@ -514,6 +1787,13 @@ impl<'resources> State<'resources> {
         update(&mut self.keys.next_skip);
         update(&mut self.keys.delete);
         update(&mut self.keys.rename);
+        update(&mut self.keys.rebind);
+        update(&mut self.keys.calibrate);
+        update(&mut self.keys.container);
+        update(&mut self.keys.speed_down);
+        update(&mut self.keys.speed_up);
+        update(&mut self.keys.repeat);
+        update(&mut self.keys.playlists);
     }
 
     fn toggle_spams(&mut self) {
@@ -529,11 +1809,57 @@ impl<'resources> State<'resources> {
     }
 
     fn double_click_disable_condition(&self) -> bool {
-        io::is_down(Keys::CANCEL_DC) || self.spam_right.is_active()
+        self.keys.cancel_dc.is_down() || self.spam_right.is_active()
+    }
+
+    // the game may have been moved or resized since the last time
+    fn update_window(&mut self) -> Result<()> {
+        self.client = match self.backend.find_window(&self.game.window)? {
+            Some(rect) => (Coord(rect.x, rect.y), (rect.width, rect.height)),
+            None => (Coord(0, 0), self.game.resolution),
+        };
+
+        let (origin, size) = self.client;
+        self.layout = Layout::new(size, self.game.gui_scale).offset(origin);
+        self.geometry = self.geometry_of(self.container);
+
+        Ok(())
+    }
+
+    // as of the last `update_window`
+    fn geometry_of(&self, container: Container) -> Geometry {
+        match self.calibration(container) {
+            Some(calibration) => calibration.geometry.offset(self.client.0),
+            None => Geometry::new(&self.layout, container),
+        }
+    }
+
+    // the calibration if it applies to the game as of the last `update_window`
+    fn calibration(&self, container: Container) -> Option<&Calibration> {
+        let (_, size) = self.client;
+
+        self.calibration.as_ref().filter(|calibration| {
+            calibration.resolution == size && container == Container::CraftingTable
+        })
+    }
+
+    fn profile(&self) -> Profile {
+        let (_, resolution) = self.client;
+
+        Profile {
+            resolution,
+            gui_scale: self.game.gui_scale,
+            calibrated: self.calibration(self.container).is_some(),
+        }
+    }
+
+    fn set_cursor(&self, gui: Coord) -> Result<()> {
+        let Coord(x, y) = self.layout.screen(gui);
+        self.backend.set_cursor(x, y)
     }
 
-    fn reload_recipes(&mut self, resources: &'resources Resources) -> Result<()> {
-        self.recipes = Recipes::new(resources)?;
+    fn reload_recipes(&mut self) -> Result<()> {
+        self.recipes = Recipes::new(self.recipes.dir())?;
         Ok(())
     }
 }
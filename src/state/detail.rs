@@ -1,19 +1,72 @@
-use crate::grid::Grid;
-use std::time::Instant;
+use crate::{
+    coord::Coord,
+    grid::{Geometry, Grid},
+    io::{Frames, MouseButton, VirtualKey},
+    playlist::Playlist,
+    recipe::{Recipe, Step, Waypoint},
+    state::key::{Capture, Chord},
+};
+use anyhow::Result;
+use image::RgbImage;
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
+#[derive(Default)]
 pub enum Detail {
+    #[default]
     Idle,
     Recording {
-        clicks: Vec<Grid>,
+        steps: Vec<Step>,
         count: usize,
+        drag: Option<Drag>,
+        timing: Option<Timing>,
+        frames: Frames,
     },
     Naming {
-        clicks: Vec<Grid>,
+        steps: Vec<Step>,
+        timing: Option<Box<[Timed]>>,
+        frames: Frames,
         name: String,
         draw_required: bool,
     },
+    // one stage unless a playlist is played
     Playing {
-        clicks: Box<[(Grid, Cursor)]>,
+        stages: Box<[Schedule]>,
+        stage: usize,
+        progress: Progress,
+        // nothing is sent to the game if this is set
+        dry_run: Option<DryRun>,
+    },
+    // the slots to take from differ from the thumbnail of the first recipe
+    Mismatched {
+        stages: Box<[(PathBuf, Repeat)]>,
+        dry_run: bool,
+        slots: Box<[Grid]>,
+    },
+    Repeating {
+        count: String,
+        draw_required: bool,
+    },
+    Choosing {
+        playlists: Box<[(String, Playlist)]>,
+        selected: usize,
+    },
+    Editing {
+        dir: PathBuf,
+        recipe: Recipe,
+        selected: usize,
+        // since it was opened or saved
+        edited: bool,
+    },
+    // shows the steps one after another without playing them
+    Previewing {
+        recipe: Recipe,
+        current: usize,
         origin: Instant,
     },
     TradingFirst {
@@ -31,12 +84,347 @@ pub enum Detail {
         name: String,
         draw_required: bool,
     },
+    // the ID of the item in the slot, which is kept in the library
+    Learning {
+        slot: RgbImage,
+        name: String,
+        draw_required: bool,
+    },
+    Calibrating {
+        points: Vec<Coord>,
+    },
+    Validating {
+        slots: Box<[Grid]>,
+        current: Option<usize>,
+        origin: Instant,
+    },
+    Rebinding {
+        selected: usize,
+        capture: Option<Capture>,
+        conflict: Option<Chord>,
+    },
+}
+
+// the slots passed over while the button is held, which is a click if there is only one
+pub struct Drag {
+    pub button: MouseButton,
+    pub slots: Vec<Grid>,
+}
+
+// what timed recording keeps of each step besides what it does
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timed {
+    // since the previous step
+    pub delay: Duration,
+    pub path: Box<[Waypoint]>,
+}
+
+// the cursor is followed while no button is held, and a step begins when its button is pressed
+#[derive(Default)]
+pub struct Timing {
+    // when the last step began, unless none has
+    last: Option<Instant>,
+    // where the cursor was last seen
+    point: Option<Coord>,
+    path: Vec<Waypoint>,
+    // of a drag which has not ended yet
+    pending: Option<Timed>,
+    steps: Vec<Timed>,
+}
+
+impl Timing {
+    pub fn follow(&mut self, now: Instant, point: Coord) {
+        if let Some(last) = self.last
+            && self.pending.is_none()
+            && self.point != Some(point)
+        {
+            self.path.push(Waypoint {
+                at: now - last,
+                to: point,
+            });
+        }
+
+        self.point = Some(point);
+    }
+
+    // the first step is played right away
+    pub fn begin(&mut self, now: Instant, point: Coord) {
+        self.point = Some(point);
+
+        let timed = match self.last.replace(now) {
+            Some(last) => Timed {
+                delay: now - last,
+                path: std::mem::take(&mut self.path).into(),
+            },
+            None => Timed {
+                delay: Duration::ZERO,
+                path: Box::default(),
+            },
+        };
+
+        self.pending = Some(timed);
+    }
+
+    pub fn end(&mut self) {
+        self.steps.extend(self.pending.take());
+    }
+
+    pub fn undo(&mut self) {
+        self.steps.pop();
+    }
+
+    pub fn into_steps(self) -> Box<[Timed]> {
+        self.steps.into()
+    }
+}
+
+// a recipe as played back
+pub struct Schedule {
+    // of the recipe
+    pub name: String,
+    pub dir: PathBuf,
+    pub ops: Box<[(Duration, Op)]>,
+    // from the start of a run to that of the next
+    pub length: Duration,
+    // the slots to look at before each run
+    pub inputs: Box<[Grid]>,
+    pub repeat: Repeat,
+}
+
+// how far the current run of a stage has come
+pub struct Progress {
+    // counted from 1
+    pub run: u32,
+    // of the op
+    pub next: usize,
+    // of the run
+    pub origin: Instant,
+    // taken from by `Step::Fetch` in this run, which may not look empty on the screen yet
+    pub fetched: Vec<Grid>,
+    // whether the inputs were looked at, once the run was due
+    pub checked: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Repeat {
+    Times(u32),
+    UntilAbort,
+}
+
+impl Schedule {
+    pub fn new(
+        name: String,
+        dir: PathBuf,
+        recipe: Recipe,
+        interval: Duration,
+        speed: f64,
+        repeat: Repeat,
+    ) -> Self {
+        let inputs = recipe.inputs().into();
+        let (ops, length) = Op::schedule(recipe, interval, speed);
+
+        Self {
+            name,
+            dir,
+            ops,
+            length,
+            inputs,
+            repeat,
+        }
+    }
+}
+
+impl Progress {
+    pub const fn new(run: u32, origin: Instant) -> Self {
+        Self {
+            run,
+            next: 0,
+            origin,
+            fetched: Vec::new(),
+            checked: false,
+        }
+    }
+}
+
+impl Repeat {
+    pub const fn is_last(&self, run: u32) -> bool {
+        match self {
+            Self::Times(times) => *times <= run,
+            Self::UntilAbort => false,
+        }
+    }
+}
+
+// what playback does, one at a time
+#[derive(Debug, PartialEq)]
+pub enum Op {
+    Move(Grid),
+    // to the first slot of the inventory or the hotbar which holds the item
+    Find(String),
+    // to a point of the client area
+    Point(Coord),
+    Click(MouseButton),
+    Down(MouseButton),
+    Up(MouseButton),
+    KeyDown(VirtualKey),
+    KeyUp(VirtualKey),
+    Tap(VirtualKey),
+}
+
+impl Op {
+    // each op an interval after the previous one, and the first of a step also its delay after the
+    // first of the previous step, all divided by `speed`. the times are from the start of playback, which lasts as long as the
+    // returned duration.
+    pub fn schedule(
+        recipe: Recipe,
+        interval: Duration,
+        speed: f64,
+    ) -> (Box<[(Duration, Self)]>, Duration) {
+        let interval = recipe.interval.unwrap_or(interval);
+        let mut ops = Vec::new();
+        let mut at = Duration::ZERO;
+        // of the previous step, which the delay and the path of the next one are measured from
+        let mut step_start = Duration::ZERO;
+
+        for entry in recipe.steps {
+            let interval = entry.interval.unwrap_or(interval).div_f64(speed);
+
+            // not before the inputs of the previous step, so that the ops stay in order
+            for waypoint in entry.path {
+                let point_at = (step_start + waypoint.at.div_f64(speed)).max(at);
+                ops.push((point_at, Self::Point(waypoint.to)));
+            }
+
+            step_start = at.max(step_start + entry.delay.div_f64(speed));
+            at = step_start;
+
+            for op in Self::of(entry.step) {
+                ops.push((at, op));
+                at += interval;
+            }
+        }
+
+        (ops.into(), at)
+    }
+
+    fn of(step: Step) -> Vec<Self> {
+        let (grid, ops) = match step {
+            Step::Left(grid) => (grid, vec![Self::Click(MouseButton::Left)]),
+            Step::Right(grid) => (grid, vec![Self::Click(MouseButton::Right)]),
+            Step::ShiftLeft(grid) => (
+                grid,
+                vec![
+                    Self::KeyDown(VirtualKey::LShift),
+                    Self::Click(MouseButton::Left),
+                    Self::KeyUp(VirtualKey::LShift),
+                ],
+            ),
+            Step::Middle(grid) => (grid, vec![Self::Click(MouseButton::Middle)]),
+            Step::Swap(grid, number) => (
+                grid,
+                vec![Self::Tap(VirtualKey::HOTBAR[number as usize - 1])],
+            ),
+            Step::Drop(grid) => (grid, vec![Self::Tap(VirtualKey::Q)]),
+            Step::DragLeft(slots) => return Self::drag(MouseButton::Left, slots),
+            Step::DragRight(slots) => return Self::drag(MouseButton::Right, slots),
+            Step::Fetch(item, grid) => {
+                return vec![
+                    Self::Find(item),
+                    Self::Click(MouseButton::Left),
+                    Self::Move(grid),
+                    Self::Click(MouseButton::Left),
+                ];
+            }
+        };
+
+        std::iter::once(Self::Move(grid)).chain(ops).collect()
+    }
+
+    fn drag(button: MouseButton, slots: Box<[Grid]>) -> Vec<Self> {
+        let mut slots = slots.into_iter().map(Self::Move);
+        let mut ops: Vec<_> = slots.next().into_iter().collect();
+
+        ops.push(Self::Down(button));
+        ops.extend(slots);
+        ops.push(Self::Up(button));
+
+        ops
+    }
 }
 
-pub enum Cursor {
-    New,
-    Moved,
-    Clicked,
+// what playback would do, written to the window and a file instead of sent to the game
+pub struct DryRun {
+    start: Instant,
+    // where the cursor would be
+    slot: Option<Grid>,
+    point: Option<Coord>,
+    // the latest ones, since the file holds all of them
+    pub lines: VecDeque<String>,
+    file: BufWriter<File>,
+}
+
+impl DryRun {
+    // as many as the window has rows for
+    pub const LINES: usize = 10;
+
+    pub fn new(path: impl AsRef<Path>, start: Instant) -> Result<Self> {
+        Ok(Self {
+            start,
+            slot: None,
+            point: None,
+            lines: VecDeque::with_capacity(Self::LINES),
+            file: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    // e.g. "120ms Table(0, 0) Click(Left) at (830, 386)", where `geometry` is on the screen and
+    // `client` is the origin of the client area
+    pub fn log(&mut self, at: Instant, op: &Op, geometry: &Geometry, client: Coord) -> Result<()> {
+        match op {
+            Op::Move(grid) => {
+                self.slot = Some(*grid);
+                self.point = grid.coord(geometry);
+            }
+            Op::Point(point) => {
+                let point = client.add(*point);
+                self.slot = Grid::at(point, geometry);
+                self.point = Some(point);
+            }
+            // which is logged as a move if it is found
+            Op::Find(_) => {
+                self.slot = None;
+                self.point = None;
+            }
+            _ => (),
+        }
+
+        let time = at.saturating_duration_since(self.start).as_millis();
+        let slot = self
+            .slot
+            .map_or_else(|| "-".to_string(), |grid| format!("{grid:?}"));
+        let line = match (op, self.point) {
+            (Op::Find(_), _) => format!("{time}ms {slot} {op:?} (not found)"),
+            (_, Some(Coord(x, y))) => format!("{time}ms {slot} {op:?} at ({x}, {y})"),
+            (_, None) => format!("{time}ms {slot} {op:?} (not in this container)"),
+        };
+
+        writeln!(self.file, "{line}")?;
+
+        if self.lines.len() == Self::LINES {
+            self.lines.pop_front();
+        }
+
+        self.lines.push_back(line);
+
+        Ok(())
+    }
+
+    // dropping the file would discard what is left of it, and the error with it
+    pub fn finish(mut self) -> Result<()> {
+        self.file.flush()?;
+
+        Ok(())
+    }
 }
 
 pub enum TradeFirst {
@@ -51,9 +439,3 @@ pub enum TradeSecond {
     MovedToLeft,
     LeftClicked,
 }
-
-impl Default for Detail {
-    fn default() -> Self {
-        Self::Idle
-    }
-}
@@ -1,14 +1,17 @@
-use windows::Win32::UI::Input::KeyboardAndMouse::{self as kam, VIRTUAL_KEY};
+use crate::io::{InputBackend, VirtualKey};
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
 
 pub struct Key {
-    vkeys: Box<[VIRTUAL_KEY]>,
+    vkeys: Box<[VirtualKey]>,
     previous: bool,
     is_pressed: bool,
     is_released: bool,
 }
 
 impl Key {
-    pub fn single(vkey: VIRTUAL_KEY) -> Self {
+    pub fn single(vkey: VirtualKey) -> Self {
         Self {
             vkeys: Box::new([vkey]),
             previous: false,
@@ -17,7 +20,7 @@ impl Key {
         }
     }
 
-    pub fn multiple(vkeys: impl Into<Box<[VIRTUAL_KEY]>>) -> Self {
+    pub fn multiple(vkeys: impl Into<Box<[VirtualKey]>>) -> Self {
         Self {
             vkeys: vkeys.into(),
             previous: false,
@@ -26,8 +29,8 @@ impl Key {
         }
     }
 
-    pub fn update(&mut self, is_disabled: bool) {
-        let is_down = !is_disabled && self.vkeys.iter().all(|vkey| crate::io::is_down(*vkey));
+    pub fn update(&mut self, is_disabled: bool, backend: &dyn InputBackend) {
+        let is_down = !is_disabled && self.vkeys.iter().all(|vkey| backend.is_down(*vkey));
 
         self.is_pressed = is_down && !self.previous;
         self.is_released = !is_down && self.previous;
@@ -41,6 +44,127 @@ impl Key {
     pub const fn is_released(&self) -> bool {
         self.is_released
     }
+
+    pub const fn is_down(&self) -> bool {
+        self.previous
+    }
+}
+
+// the controls of the game besides the hotbar keys, which are not rebound
+const MIDDLE: VirtualKey = VirtualKey::MButton;
+const DROP: VirtualKey = VirtualKey::Q;
+
+// keys which have to be held at once, written as e.g. "LCtrl+MButton" in the config file
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Chord(Box<[VirtualKey]>);
+
+impl Chord {
+    pub fn new(vkeys: impl Into<Box<[VirtualKey]>>) -> Self {
+        Self(vkeys.into())
+    }
+
+    pub fn vkeys(&self) -> &[VirtualKey] {
+        &self.0
+    }
+
+    // the order does not matter to `Key::update`
+    pub fn conflicts_with(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len() && self.0.iter().all(|vkey| other.0.contains(vkey))
+    }
+}
+
+impl FromStr for Chord {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut vkeys = Vec::new();
+
+        for name in s.split('+').map(str::trim) {
+            let Some(vkey) = VirtualKey::from_name(name) else {
+                bail!("unknown key name `{name}`");
+            };
+
+            if vkeys.contains(&vkey) {
+                bail!("`{name}` appears more than once");
+            }
+
+            vkeys.push(vkey);
+        }
+
+        Ok(Self::new(vkeys))
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, vkey) in self.0.iter().enumerate() {
+            if i != 0 {
+                f.write_str("+")?;
+            }
+
+            f.write_str(vkey.name())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for Chord {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<Chord> for String {
+    fn from(value: Chord) -> Self {
+        value.to_string()
+    }
+}
+
+// polls like `Key::update` does: keys are collected as they go down and the chord is complete once
+// every one of them is up again
+pub struct Capture {
+    ignored: Vec<VirtualKey>,
+    held: Vec<VirtualKey>,
+}
+
+impl Capture {
+    // whatever is down already (e.g. the key which started the capture) is ignored until released
+    pub fn new(backend: &dyn InputBackend) -> Self {
+        Self {
+            ignored: Self::down(backend).collect(),
+            held: Vec::new(),
+        }
+    }
+
+    pub fn held(&self) -> &[VirtualKey] {
+        &self.held
+    }
+
+    pub fn update(&mut self, backend: &dyn InputBackend) -> Option<Chord> {
+        let down: Vec<_> = Self::down(backend).collect();
+        self.ignored.retain(|vkey| down.contains(vkey));
+
+        for vkey in &down {
+            if !self.ignored.contains(vkey) && !self.held.contains(vkey) {
+                self.held.push(*vkey);
+            }
+        }
+
+        let is_complete =
+            !self.held.is_empty() && self.held.iter().all(|vkey| !down.contains(vkey));
+        is_complete.then(|| Chord::new(std::mem::take(&mut self.held)))
+    }
+
+    fn down(backend: &dyn InputBackend) -> impl Iterator<Item = VirtualKey> {
+        VirtualKey::ALL
+            .iter()
+            .copied()
+            .filter(|vkey| backend.is_down(*vkey))
+    }
 }
 
 pub struct Keys {
@@ -64,52 +188,349 @@ pub struct Keys {
     pub next_skip: Key,
     pub delete: Key,
     pub rename: Key,
+    pub rebind: Key,
+    pub calibrate: Key,
+    pub container: Key,
+    pub speed_down: Key,
+    pub speed_up: Key,
+    pub repeat: Key,
+    pub playlists: Key,
+    pub record_timed: Key,
+    pub undo: Key,
+    pub edit: Key,
+    pub duplicate: Key,
+    pub move_up: Key,
+    pub move_down: Key,
+    pub preview: Key,
+    pub dry_run: Key,
+    pub learn: Key,
+    // the controls of the game with their defaults, which are recorded as they are
+    pub middle: Key,
+    pub drop: Key,
+    pub hotbar: [Key; 9],
 }
 
 impl Keys {
-    const LEFT: VIRTUAL_KEY = kam::VK_Z;
-    const RIGHT: VIRTUAL_KEY = kam::VK_X;
-    const SPACE: VIRTUAL_KEY = kam::VK_C;
-    const RECORD: VIRTUAL_KEY = kam::VK_B;
-    const PLAY: VIRTUAL_KEY = kam::VK_G;
-    const CLICK: VIRTUAL_KEY = kam::VK_LBUTTON;
-    const RIGHT_CLICK: VIRTUAL_KEY = kam::VK_RBUTTON;
-    const PREV: VIRTUAL_KEY = kam::VK_XBUTTON2;
-    const NEXT: VIRTUAL_KEY = kam::VK_XBUTTON1;
-    const DOUBLE_CLICK: VIRTUAL_KEY = kam::VK_TAB;
-    const BEGIN_TRADE: VIRTUAL_KEY = kam::VK_R;
-    const END_TRADE: VIRTUAL_KEY = kam::VK_LSHIFT;
-    const ABORT: VIRTUAL_KEY = kam::VK_OEM_3;
-    const LOCK: &[VIRTUAL_KEY] = &[kam::VK_LCONTROL, kam::VK_MBUTTON];
-    pub const CANCEL_DC: VIRTUAL_KEY = kam::VK_LCONTROL;
-    const CONFIRM: VIRTUAL_KEY = kam::VK_RETURN;
-    const PREV_SKIP: &[VIRTUAL_KEY] = &[kam::VK_LCONTROL, Self::PREV];
-    const NEXT_SKIP: &[VIRTUAL_KEY] = &[kam::VK_LCONTROL, Self::NEXT];
-    const DELETE: &[VIRTUAL_KEY] = &[kam::VK_LMENU, kam::VK_D];
-    const RENAME: &[VIRTUAL_KEY] = &[kam::VK_LMENU, kam::VK_A];
-
-    pub fn new() -> Self {
+    pub fn new(bindings: &Bindings) -> Self {
+        let key = |chord: &Chord| match chord.vkeys() {
+            [vkey] => Key::single(*vkey),
+            vkeys => Key::multiple(vkeys),
+        };
+
         Self {
-            left: Key::single(Self::LEFT),
-            right: Key::single(Self::RIGHT),
-            space: Key::single(Self::SPACE),
-            record: Key::single(Self::RECORD),
-            play: Key::single(Self::PLAY),
-            click: Key::single(Self::CLICK),
-            right_click: Key::single(Self::RIGHT_CLICK),
-            prev: Key::single(Self::PREV),
-            next: Key::single(Self::NEXT),
-            double_click: Key::single(Self::DOUBLE_CLICK),
-            begin_trade: Key::single(Self::BEGIN_TRADE),
-            end_trade: Key::single(Self::END_TRADE),
-            abort: Key::single(Self::ABORT),
-            lock: Key::multiple(Self::LOCK),
-            cancel_dc: Key::single(Self::CANCEL_DC),
-            confirm: Key::single(Self::CONFIRM),
-            prev_skip: Key::multiple(Self::PREV_SKIP),
-            next_skip: Key::multiple(Self::NEXT_SKIP),
-            delete: Key::multiple(Self::DELETE),
-            rename: Key::multiple(Self::RENAME),
+            left: key(&bindings.left),
+            right: key(&bindings.right),
+            space: key(&bindings.space),
+            record: key(&bindings.record),
+            play: key(&bindings.play),
+            click: key(&bindings.click),
+            right_click: key(&bindings.right_click),
+            prev: key(&bindings.prev),
+            next: key(&bindings.next),
+            double_click: key(&bindings.double_click),
+            begin_trade: key(&bindings.begin_trade),
+            end_trade: key(&bindings.end_trade),
+            abort: key(&bindings.abort),
+            lock: key(&bindings.lock),
+            cancel_dc: key(&bindings.cancel_dc),
+            confirm: key(&bindings.confirm),
+            prev_skip: key(&bindings.prev_skip),
+            next_skip: key(&bindings.next_skip),
+            delete: key(&bindings.delete),
+            rename: key(&bindings.rename),
+            rebind: key(&bindings.rebind),
+            calibrate: key(&bindings.calibrate),
+            container: key(&bindings.container),
+            speed_down: key(&bindings.speed_down),
+            speed_up: key(&bindings.speed_up),
+            repeat: key(&bindings.repeat),
+            playlists: key(&bindings.playlists),
+            record_timed: key(&bindings.record_timed),
+            undo: key(&bindings.undo),
+            edit: key(&bindings.edit),
+            duplicate: key(&bindings.duplicate),
+            move_up: key(&bindings.move_up),
+            move_down: key(&bindings.move_down),
+            preview: key(&bindings.preview),
+            dry_run: key(&bindings.dry_run),
+            learn: key(&bindings.learn),
+            middle: Key::single(MIDDLE),
+            drop: Key::single(DROP),
+            hotbar: VirtualKey::HOTBAR.map(Key::single),
         }
     }
 }
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Bindings {
+    pub left: Chord,
+    pub right: Chord,
+    pub space: Chord,
+    pub record: Chord,
+    pub play: Chord,
+    pub click: Chord,
+    pub right_click: Chord,
+    pub prev: Chord,
+    pub next: Chord,
+    pub double_click: Chord,
+    pub begin_trade: Chord,
+    pub end_trade: Chord,
+    pub abort: Chord,
+    pub lock: Chord,
+    pub cancel_dc: Chord,
+    pub confirm: Chord,
+    pub prev_skip: Chord,
+    pub next_skip: Chord,
+    pub delete: Chord,
+    pub rename: Chord,
+    pub rebind: Chord,
+    pub calibrate: Chord,
+    pub container: Chord,
+    pub speed_down: Chord,
+    pub speed_up: Chord,
+    pub repeat: Chord,
+    pub playlists: Chord,
+    pub record_timed: Chord,
+    pub undo: Chord,
+    pub edit: Chord,
+    pub duplicate: Chord,
+    pub move_up: Chord,
+    pub move_down: Chord,
+    pub preview: Chord,
+    pub dry_run: Chord,
+    pub learn: Chord,
+}
+
+impl Bindings {
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &Chord)> {
+        [
+            ("left", &self.left),
+            ("right", &self.right),
+            ("space", &self.space),
+            ("record", &self.record),
+            ("play", &self.play),
+            ("click", &self.click),
+            ("right_click", &self.right_click),
+            ("prev", &self.prev),
+            ("next", &self.next),
+            ("double_click", &self.double_click),
+            ("begin_trade", &self.begin_trade),
+            ("end_trade", &self.end_trade),
+            ("abort", &self.abort),
+            ("lock", &self.lock),
+            ("cancel_dc", &self.cancel_dc),
+            ("confirm", &self.confirm),
+            ("prev_skip", &self.prev_skip),
+            ("next_skip", &self.next_skip),
+            ("delete", &self.delete),
+            ("rename", &self.rename),
+            ("rebind", &self.rebind),
+            ("calibrate", &self.calibrate),
+            ("container", &self.container),
+            ("speed_down", &self.speed_down),
+            ("speed_up", &self.speed_up),
+            ("repeat", &self.repeat),
+            ("playlists", &self.playlists),
+            ("record_timed", &self.record_timed),
+            ("undo", &self.undo),
+            ("edit", &self.edit),
+            ("duplicate", &self.duplicate),
+            ("move_up", &self.move_up),
+            ("move_down", &self.move_down),
+            ("preview", &self.preview),
+            ("dry_run", &self.dry_run),
+            ("learn", &self.learn),
+        ]
+        .into_iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&'static str, &mut Chord)> {
+        [
+            ("left", &mut self.left),
+            ("right", &mut self.right),
+            ("space", &mut self.space),
+            ("record", &mut self.record),
+            ("play", &mut self.play),
+            ("click", &mut self.click),
+            ("right_click", &mut self.right_click),
+            ("prev", &mut self.prev),
+            ("next", &mut self.next),
+            ("double_click", &mut self.double_click),
+            ("begin_trade", &mut self.begin_trade),
+            ("end_trade", &mut self.end_trade),
+            ("abort", &mut self.abort),
+            ("lock", &mut self.lock),
+            ("cancel_dc", &mut self.cancel_dc),
+            ("confirm", &mut self.confirm),
+            ("prev_skip", &mut self.prev_skip),
+            ("next_skip", &mut self.next_skip),
+            ("delete", &mut self.delete),
+            ("rename", &mut self.rename),
+            ("rebind", &mut self.rebind),
+            ("calibrate", &mut self.calibrate),
+            ("container", &mut self.container),
+            ("speed_down", &mut self.speed_down),
+            ("speed_up", &mut self.speed_up),
+            ("repeat", &mut self.repeat),
+            ("playlists", &mut self.playlists),
+            ("record_timed", &mut self.record_timed),
+            ("undo", &mut self.undo),
+            ("edit", &mut self.edit),
+            ("duplicate", &mut self.duplicate),
+            ("move_up", &mut self.move_up),
+            ("move_down", &mut self.move_down),
+            ("preview", &mut self.preview),
+            ("dry_run", &mut self.dry_run),
+            ("learn", &mut self.learn),
+        ]
+        .into_iter()
+    }
+
+    // the first action other than `name` which is bound to the same keys, or the control of the
+    // game they are kept for
+    pub fn conflict(&self, name: &str, chord: &Chord) -> Option<&'static str> {
+        self.iter()
+            .find(|(other, bound)| *other != name && chord.conflicts_with(bound))
+            .map(|(other, _)| other)
+            .or_else(|| Self::reserved(chord))
+    }
+
+    // the controls of the game in `Keys`, which recording and playback rely on
+    fn reserved(chord: &Chord) -> Option<&'static str> {
+        match chord.vkeys() {
+            [MIDDLE] => Some("middle"),
+            [DROP] => Some("drop"),
+            [vkey] if VirtualKey::HOTBAR.contains(vkey) => Some("hotbar"),
+            _ => None,
+        }
+    }
+
+    // a chord may extend another one (e.g. `prev_skip` and `prev`) but two actions must not share one
+    pub fn validate(&self) -> Result<()> {
+        for (name, chord) in self.iter() {
+            if let Some(control) = Self::reserved(chord) {
+                bail!(
+                    "`keys.{name}` is bound to \"{chord}\", which is kept for the {control} control of the game"
+                );
+            }
+
+            if let Some(other) = self.conflict(name, chord) {
+                bail!("`keys.{name}` and `keys.{other}` are both bound to \"{chord}\"");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use VirtualKey as V;
+
+        let single = |vkey| Chord::new([vkey]);
+        let prev = V::XButton2;
+        let next = V::XButton1;
+
+        Self {
+            left: single(V::Z),
+            right: single(V::X),
+            space: single(V::C),
+            record: single(V::B),
+            play: single(V::G),
+            click: single(V::LButton),
+            right_click: single(V::RButton),
+            prev: single(prev),
+            next: single(next),
+            double_click: single(V::Tab),
+            begin_trade: single(V::R),
+            end_trade: single(V::LShift),
+            abort: single(V::Grave),
+            lock: Chord::new([V::LControl, V::MButton]),
+            cancel_dc: single(V::LControl),
+            confirm: single(V::Return),
+            prev_skip: Chord::new([V::LControl, prev]),
+            next_skip: Chord::new([V::LControl, next]),
+            delete: Chord::new([V::LAlt, V::D]),
+            rename: Chord::new([V::LAlt, V::A]),
+            rebind: Chord::new([V::LAlt, V::K]),
+            calibrate: Chord::new([V::LAlt, V::L]),
+            container: Chord::new([V::LAlt, V::S]),
+            speed_down: Chord::new([V::LAlt, V::H]),
+            speed_up: Chord::new([V::LAlt, V::J]),
+            repeat: Chord::new([V::LAlt, V::N]),
+            playlists: Chord::new([V::LAlt, V::P]),
+            record_timed: Chord::new([V::LAlt, V::T]),
+            undo: Chord::new([V::LAlt, V::U]),
+            edit: Chord::new([V::LAlt, V::E]),
+            duplicate: Chord::new([V::LAlt, V::V]),
+            move_up: Chord::new([V::LAlt, V::I]),
+            move_down: Chord::new([V::LAlt, V::O]),
+            preview: Chord::new([V::LAlt, V::W]),
+            dry_run: Chord::new([V::LAlt, V::Y]),
+            learn: Chord::new([V::LAlt, V::M]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_names_and_aliases() -> Result<()> {
+        assert_eq!(
+            "LCtrl+MButton".parse::<Chord>()?,
+            Chord::new([VirtualKey::LControl, VirtualKey::MButton])
+        );
+        assert_eq!(
+            "alt + d".parse::<Chord>()?,
+            Chord::new([VirtualKey::LAlt, VirtualKey::D])
+        );
+        assert_eq!("`".parse::<Chord>()?, Chord::new([VirtualKey::Grave]));
+        assert_eq!("F12".parse::<Chord>()?.to_string(), "F12");
+        assert_eq!("ctrl+1".parse::<Chord>()?.to_string(), "LCtrl+1");
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unknown_and_repeated_names() {
+        let message = |s: &str| s.parse::<Chord>().err().unwrap().to_string();
+
+        assert_eq!(message("LCtrl+Hyper"), "unknown key name `Hyper`");
+        assert_eq!(message(""), "unknown key name ``");
+        assert_eq!(message("Ctrl+LCtrl"), "`LCtrl` appears more than once");
+    }
+
+    #[test]
+    fn detects_conflicts_regardless_of_order() {
+        let mut bindings = Bindings::default();
+        assert!(bindings.validate().is_ok());
+
+        bindings.rename = Chord::new([VirtualKey::D, VirtualKey::LAlt]);
+        assert_eq!(
+            bindings.validate().err().unwrap().to_string(),
+            "`keys.delete` and `keys.rename` are both bound to \"LAlt+D\""
+        );
+    }
+
+    #[test]
+    fn rejects_the_controls_of_the_game() {
+        let mut bindings = Bindings::default();
+        let message = |bindings: &Bindings| bindings.validate().err().unwrap().to_string();
+
+        bindings.play = Chord::new([VirtualKey::Q]);
+        assert_eq!(
+            message(&bindings),
+            "`keys.play` is bound to \"Q\", which is kept for the drop control of the game"
+        );
+
+        bindings.play = Chord::new([VirtualKey::Num3]);
+        assert_eq!(bindings.conflict("play", &bindings.play), Some("hotbar"));
+
+        // extending them is fine, as with the other chords
+        bindings.play = Chord::new([VirtualKey::LAlt, VirtualKey::Q]);
+        assert!(bindings.validate().is_ok());
+    }
+}
@@ -1,7 +1,4 @@
-use crate::{
-    io,
-    resources::{Resources, Textures},
-};
+use crate::io;
 use anyhow::{Result, anyhow, bail};
 use std::{
     fmt,
@@ -9,10 +6,10 @@ use std::{
 };
 
 #[derive(Default)]
-pub struct Recipes<'resources> {
+pub struct Recipes {
+    dir: PathBuf,
     paths: Box<[PathChar]>,
     index: Option<usize>,
-    textures: Option<Textures<'resources>>,
 }
 
 struct PathChar {
@@ -20,11 +17,10 @@ struct PathChar {
     first_char: char,
 }
 
-impl<'resources> Recipes<'resources> {
-    pub const RECIPES: &'static str = r"D:\rust\mctool\recipes";
-
-    pub fn new(resources: &'resources Resources) -> Result<Self> {
-        let paths_opt: Option<Box<[PathChar]>> = io::recipes(Self::RECIPES)?
+impl Recipes {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        let paths_opt: Option<Box<[PathChar]>> = io::recipes(&dir)?
             .into_iter()
             .map(|path| {
                 let first_char = path.file_name()?.to_str()?.chars().next()?;
@@ -37,20 +33,11 @@ impl<'resources> Recipes<'resources> {
 
         let index = Self::last_index(&paths);
 
-        let textures = match Self::get_ext(&paths, index)? {
-            None => None,
-            Some(PathChar { path, .. }) => Some(resources.load_textures(path)?),
-        };
-
-        Ok(Self {
-            paths,
-            index,
-            textures,
-        })
+        Ok(Self { dir, paths, index })
     }
 
-    pub const fn textures(&self) -> Option<&Textures<'_>> {
-        self.textures.as_ref()
+    pub fn dir(&self) -> &Path {
+        &self.dir
     }
 
     pub const fn len(&self) -> usize {
@@ -61,35 +48,33 @@ impl<'resources> Recipes<'resources> {
         self.get().map(|opt| opt.map(|PathChar { path, .. }| path))
     }
 
-    pub fn increment(&mut self, resources: &'resources Resources) -> Result<()> {
+    // left as it is if the path is not among them
+    pub fn select(&mut self, path: &Path) {
+        if let Some(index) = self
+            .paths
+            .iter()
+            .position(|path_char| path_char.path == path)
+        {
+            self.index = Some(index);
+        }
+    }
+
+    pub const fn increment(&mut self) {
         self.increment_detail();
-        self.update_textures(resources)
     }
 
-    pub fn decrement(&mut self, resources: &'resources Resources) -> Result<()> {
+    pub const fn decrement(&mut self) {
         self.decrement_detail();
-        self.update_textures(resources)
     }
 
-    pub fn increment_skip(&mut self, resources: &'resources Resources) -> Result<()> {
+    pub fn increment_skip(&mut self) {
         self.skip_detail(Self::increment_detail);
-        self.update_textures(resources)
     }
 
-    pub fn decrement_skip(&mut self, resources: &'resources Resources) -> Result<()> {
+    pub fn decrement_skip(&mut self) {
         self.decrement_detail();
         self.skip_detail(Self::decrement_detail);
         self.increment_detail();
-        self.update_textures(resources)
-    }
-
-    pub fn update_textures(&mut self, resources: &'resources Resources) -> Result<()> {
-        self.textures = match self.get_path()? {
-            None => None,
-            Some(path) => Some(resources.load_textures(path)?),
-        };
-
-        Ok(())
     }
 
     const fn increment_detail(&mut self) {
@@ -109,23 +94,23 @@ impl<'resources> Recipes<'resources> {
         }
     }
 
-    pub fn delete(&mut self, resources: &'resources Resources) -> Result<()> {
+    pub fn delete(&mut self) -> Result<()> {
         if let Some(path) = self.get_path()? {
             std::fs::remove_dir_all(path)?;
-            *self = Self::new(resources)?;
+            *self = Self::new(self.dir.clone())?;
         }
 
         Ok(())
     }
 
-    pub fn rename(&mut self, to: impl AsRef<Path>, resources: &'resources Resources) -> Result<()> {
+    pub fn rename(&mut self, to: impl AsRef<Path>) -> Result<()> {
         if let Some(from) = self.get_path()? {
             if from == to.as_ref() {
                 bail!("rename not needed");
             }
 
             std::fs::rename(from, to)?;
-            *self = Self::new(resources)?;
+            *self = Self::new(self.dir.clone())?;
         }
 
         Ok(())
@@ -181,7 +166,7 @@ impl<'resources> Recipes<'resources> {
     }
 }
 
-impl fmt::Display for Recipes<'_> {
+impl fmt::Display for Recipes {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let str = self
             .get_path()
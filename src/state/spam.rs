@@ -1,24 +1,28 @@
+use crate::io::InputBackend;
+use anyhow::Result;
 use std::time::{Duration, Instant};
 
+type Callback = Box<dyn Fn(&dyn InputBackend) -> Result<()>>;
+
 pub struct Spam {
     is_active: bool,
     is_down: bool,
     last: Instant,
     interval: Duration,
-    on_down: Box<dyn Fn()>,
-    on_up: Box<dyn Fn()>,
+    on_down: Callback,
+    on_up: Callback,
 }
 
 impl Spam {
-    pub fn new<F1, F2>(interval: Duration, on_down: F1, on_up: F2) -> Self
+    pub fn new<F1, F2>(interval: Duration, now: Instant, on_down: F1, on_up: F2) -> Self
     where
-        F1: Fn() + 'static,
-        F2: Fn() + 'static,
+        F1: Fn(&dyn InputBackend) -> Result<()> + 'static,
+        F2: Fn(&dyn InputBackend) -> Result<()> + 'static,
     {
         Self {
             is_active: false,
             is_down: false,
-            last: Instant::now(),
+            last: now,
             interval,
             on_down: Box::new(on_down),
             on_up: Box::new(on_up),
@@ -33,7 +37,7 @@ impl Spam {
         self.is_active ^= true
     }
 
-    pub fn step(&mut self, now: Instant) {
+    pub fn step(&mut self, now: Instant, backend: &dyn InputBackend) -> Result<()> {
         let elapsed = now - self.last;
 
         if self.interval <= elapsed {
@@ -42,15 +46,17 @@ impl Spam {
 
             match (self.is_active, self.is_down) {
                 (true, false) => {
-                    (self.on_down)();
+                    (self.on_down)(backend)?;
                     self.is_down = true;
                 }
                 (false, false) => (),
                 (_, true) => {
-                    (self.on_up)();
+                    (self.on_up)(backend)?;
                     self.is_down = false;
                 }
             }
         }
+
+        Ok(())
     }
 }
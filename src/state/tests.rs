@@ -0,0 +1,1594 @@
+use super::*;
+use crate::recipe::Waypoint;
+use crate::{
+    clock::ManualClock,
+    io::{
+        ClientRect,
+        mock::{Output, Scripted},
+    },
+};
+use std::{
+    fs::File,
+    path::Path,
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
+use tempfile::TempDir;
+
+const fn ms(millis: u64) -> Duration {
+    Duration::from_millis(millis)
+}
+
+// fullscreen at the default resolution and GUI scale
+fn position(grid: &Grid) -> (i32, i32) {
+    grid.coord(&Geometry::new(
+        &Layout::new((1920, 1080), 3),
+        Container::CraftingTable,
+    ))
+    .unwrap()
+    .into()
+}
+
+struct Harness {
+    clock: Rc<ManualClock>,
+    script: Rc<Scripted>,
+    state: State,
+    screenshots: TempDir,
+    recipes: TempDir,
+    config: TempDir,
+    items: TempDir,
+}
+
+impl Harness {
+    fn new(recipes: &[(&str, &[Grid])]) -> Result<Self> {
+        let screenshots = TempDir::new()?;
+        let recipe_dir = TempDir::new()?;
+        let config_dir = TempDir::new()?;
+        let items = TempDir::new()?;
+
+        for (name, clicks) in recipes {
+            let dir = recipe_dir.path().join(name);
+            std::fs::create_dir(&dir)?;
+            serde_json::to_writer(File::create(dir.join(io::FILENAME_CLICKS))?, clicks)?;
+        }
+
+        let clock = Rc::new(ManualClock::new());
+        let script = Rc::new(Scripted::new(clock.clone()));
+        let config = Config {
+            path: Some(config_dir.path().join("config.toml")),
+            screenshots: screenshots.path().to_path_buf(),
+            recipes: recipe_dir.path().to_path_buf(),
+            items: items.path().to_path_buf(),
+            ..Default::default()
+        };
+        let state = State::new(Box::new(script.clone()), Box::new(clock.clone()), &config)?;
+
+        Ok(Self {
+            clock,
+            script,
+            state,
+            screenshots,
+            recipes: recipe_dir,
+            config: config_dir,
+            items,
+        })
+    }
+
+    // steps at the polling rate of the main loop
+    fn run_until(&mut self, until: Duration) -> Result<()> {
+        while self.clock.elapsed() < until {
+            self.clock.advance(ms(1));
+            self.state.step()?;
+        }
+
+        Ok(())
+    }
+
+    fn config_path(&self) -> PathBuf {
+        self.config.path().join("config.toml")
+    }
+
+    fn recipe(&self, name: &str) -> PathBuf {
+        self.recipes.path().join(name)
+    }
+
+    // the game would have saved these upon F2, one second apart
+    fn take_screenshots(&self) -> Result<()> {
+        let now = SystemTime::now();
+
+        for (i, name) in ["inventory.png", "item.png"].into_iter().enumerate() {
+            let path = self.screenshots.path().join(name);
+            image::RgbImage::new(1920, 1080).save(&path)?;
+            File::options()
+                .write(true)
+                .open(&path)?
+                .set_modified(now + Duration::from_secs(i as u64))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn steps(path: &Path) -> Result<Box<[Step]>> {
+    Ok(io::load_recipe(path.join(io::FILENAME_CLICKS))?
+        .into_steps()
+        .collect())
+}
+
+#[test]
+fn records_and_saves_clicks() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+    let table = position(&Grid::Table(0, 0));
+    let inv = position(&Grid::Inv(8, 2));
+
+    h.script.tap(ms(10), &[VirtualKey::B]);
+    h.script.click_at(ms(50), table);
+    h.script.click_at(ms(100), table);
+    h.script.click_at(ms(150), (0, 0));
+    h.script.click_at(ms(200), inv);
+    h.run_until(ms(190))?;
+
+    match h.state.detail() {
+        Detail::Recording { steps, count, .. } => {
+            assert_eq!(
+                steps,
+                &[Step::Left(Grid::Table(0, 0)), Step::Left(Grid::Table(0, 0))]
+            );
+            assert_eq!(*count, 2);
+        }
+        _ => panic!("not recording"),
+    }
+
+    h.script.tap(ms(250), &[VirtualKey::B]);
+    h.run_until(ms(300))?;
+    assert!(matches!(h.state.detail(), Detail::Naming { .. }));
+    assert!(h.state.is_locked());
+
+    h.take_screenshots()?;
+    h.state.push_text("planks");
+    h.state.push_text("x");
+    h.state.pop_text();
+    h.script.tap(ms(350), &[VirtualKey::Return]);
+    h.run_until(ms(400))?;
+
+    assert!(matches!(h.state.detail(), Detail::Idle));
+    assert_eq!(
+        steps(&h.recipe("planks"))?.as_ref(),
+        &[
+            Step::Left(Grid::Table(0, 0)),
+            Step::Left(Grid::Table(0, 0)),
+            Step::Left(Grid::Inv(8, 2))
+        ]
+    );
+    assert_eq!(
+        image::image_dimensions(h.recipe("planks").join(io::FILENAME_THUMBNAIL))?,
+        (io::INV_WIDTH, io::INV_HEIGHT)
+    );
+    assert_eq!(
+        image::image_dimensions(h.recipe("planks").join(io::FILENAME_ITEM))?,
+        (io::ITEM_WIDTH, io::ITEM_HEIGHT)
+    );
+    assert_eq!(h.state.recipes.len(), 1);
+    assert!(h.script.outputs().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn saving_without_screenshots_is_refused() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+
+    h.script.tap(ms(10), &[VirtualKey::B]);
+    h.script.tap(ms(50), &[VirtualKey::B]);
+    h.run_until(ms(100))?;
+    h.state.push_text("planks");
+    h.script.tap(ms(100), &[VirtualKey::Return]);
+    h.run_until(ms(150))?;
+
+    assert!(matches!(h.state.detail(), Detail::Naming { name, .. } if name == "planks"));
+    assert!(matches!(
+        h.script.outputs().as_slice(),
+        [Output::MessageBox(msg)] if msg.contains("two screenshots are required")
+    ));
+    assert!(!h.recipe("planks").exists());
+
+    Ok(())
+}
+
+#[test]
+fn records_the_container_and_refuses_to_play_in_another() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+    let furnace = Geometry::new(&Layout::new((1920, 1080), 3), Container::Furnace);
+    let position = |grid: &Grid| grid.coord(&furnace).unwrap().into();
+
+    h.script.tap(ms(10), &[VirtualKey::LAlt, VirtualKey::S]);
+    h.script.tap(ms(50), &[VirtualKey::LAlt, VirtualKey::S]);
+    h.script.tap(ms(100), &[VirtualKey::B]);
+    h.script.click_at(ms(150), position(&Grid::Input(0)));
+    h.script.click_at(ms(200), position(&Grid::Fuel));
+    h.script.tap(ms(250), &[VirtualKey::B]);
+    h.run_until(ms(300))?;
+    assert_eq!(h.state.container(), Container::Furnace);
+
+    h.take_screenshots()?;
+    h.state.push_text("glass");
+    h.script.tap(ms(300), &[VirtualKey::Return]);
+    h.run_until(ms(350))?;
+
+    let recipe = io::load_recipe(h.recipe("glass").join(io::FILENAME_CLICKS))?;
+    assert_eq!(recipe.container, Container::Furnace);
+    assert_eq!(
+        recipe.profile,
+        Some(Profile {
+            resolution: (1920, 1080),
+            gui_scale: 3,
+            calibrated: false
+        })
+    );
+    assert_eq!(
+        recipe.into_steps().collect::<Vec<_>>(),
+        [Step::Left(Grid::Input(0)), Step::Left(Grid::Fuel)]
+    );
+
+    // back to the crafting table after the rest of the containers
+    for i in 0..Container::ALL.len() as u64 - 2 {
+        h.script
+            .tap(ms(350 + 50 * i), &[VirtualKey::LAlt, VirtualKey::S]);
+    }
+    h.script.tap(ms(650), &[VirtualKey::G]);
+    h.run_until(ms(700))?;
+
+    assert_eq!(h.state.container(), Container::CraftingTable);
+    assert!(matches!(h.state.detail(), Detail::Idle));
+    assert!(matches!(
+        h.script.outputs().as_slice(),
+        [Output::MessageBox(msg)] if msg.contains("Furnace")
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn records_and_plays_slot_actions() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+    let at = |at, grid| h.script.move_to(ms(at), position(grid));
+
+    h.script.tap(ms(10), &[VirtualKey::B]);
+    h.script.down(ms(40), VirtualKey::LShift);
+    h.script.click_at(ms(50), position(&Grid::Inv(0, 0)));
+    h.script.up(ms(80), VirtualKey::LShift);
+    at(100, &Grid::Table(1, 1));
+    h.script.tap(ms(100), &[VirtualKey::RButton]);
+    at(150, &Grid::Craft);
+    h.script.tap(ms(150), &[VirtualKey::MButton]);
+    at(200, &Grid::Inv(1, 0));
+    h.script.tap(ms(200), &[VirtualKey::Num3]);
+    at(250, &Grid::Inv(2, 0));
+    h.script.tap(ms(250), &[VirtualKey::Q]);
+    // passing over a slot again does not count twice
+    at(300, &Grid::Table(0, 0));
+    h.script.down(ms(300), VirtualKey::LButton);
+    at(320, &Grid::Table(1, 0));
+    at(340, &Grid::Table(0, 0));
+    at(350, &Grid::Table(2, 0));
+    h.script.up(ms(360), VirtualKey::LButton);
+    h.script.tap(ms(400), &[VirtualKey::B]);
+    h.run_until(ms(450))?;
+
+    let expected = [
+        Step::ShiftLeft(Grid::Inv(0, 0)),
+        Step::Right(Grid::Table(1, 1)),
+        Step::Middle(Grid::Craft),
+        Step::Swap(Grid::Inv(1, 0), 3),
+        Step::Drop(Grid::Inv(2, 0)),
+        Step::DragLeft(Box::new([
+            Grid::Table(0, 0),
+            Grid::Table(1, 0),
+            Grid::Table(2, 0),
+        ])),
+    ];
+    assert!(matches!(
+        h.state.detail(),
+        Detail::Naming { steps, .. } if steps == &expected
+    ));
+
+    h.take_screenshots()?;
+    h.state.push_text("mixed");
+    h.script.tap(ms(450), &[VirtualKey::Return]);
+    h.script.tap(ms(500), &[VirtualKey::G]);
+    h.run_until(ms(1000))?;
+    assert_eq!(steps(&h.recipe("mixed"))?.as_ref(), &expected);
+
+    let cursor = |grid| {
+        let (x, y) = position(grid);
+        Output::Cursor(x, y)
+    };
+    let click = |button| [Output::MouseDown(button), Output::MouseUp(button)];
+    let tap = |vkey| [Output::KeyDown(vkey), Output::KeyUp(vkey)];
+
+    let outputs: Vec<_> = [
+        vec![
+            cursor(&Grid::Inv(0, 0)),
+            Output::KeyDown(VirtualKey::LShift),
+        ],
+        click(MouseButton::Left).into(),
+        vec![
+            Output::KeyUp(VirtualKey::LShift),
+            cursor(&Grid::Table(1, 1)),
+        ],
+        click(MouseButton::Right).into(),
+        vec![cursor(&Grid::Craft)],
+        click(MouseButton::Middle).into(),
+        vec![cursor(&Grid::Inv(1, 0))],
+        tap(VirtualKey::Num3).into(),
+        vec![cursor(&Grid::Inv(2, 0))],
+        tap(VirtualKey::Q).into(),
+        vec![
+            cursor(&Grid::Table(0, 0)),
+            Output::MouseDown(MouseButton::Left),
+            cursor(&Grid::Table(1, 0)),
+            cursor(&Grid::Table(2, 0)),
+            Output::MouseUp(MouseButton::Left),
+        ],
+    ]
+    .concat();
+    assert_eq!(h.script.outputs(), outputs);
+
+    Ok(())
+}
+
+#[test]
+fn plays_clicks_at_fixed_interval() -> Result<()> {
+    let recipe = [Grid::Table(1, 1), Grid::Craft];
+    let mut h = Harness::new(&[("sticks", &recipe)])?;
+
+    h.script.tap(ms(10), &[VirtualKey::G]);
+    h.run_until(ms(100))?;
+
+    assert!(matches!(h.state.detail(), Detail::Idle));
+
+    // the n-th move and click happen at the n-th multiples of `intervals.play` after the key press
+    let (x0, y0) = position(&recipe[0]);
+    let (x1, y1) = position(&recipe[1]);
+    assert_eq!(
+        h.script.log(),
+        [
+            (ms(11), Output::Cursor(x0, y0)),
+            (ms(17), Output::MouseDown(MouseButton::Left)),
+            (ms(17), Output::MouseUp(MouseButton::Left)),
+            (ms(24), Output::Cursor(x1, y1)),
+            (ms(31), Output::MouseDown(MouseButton::Left)),
+            (ms(31), Output::MouseUp(MouseButton::Left)),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn waits_for_step_delays() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+    let mut recipe = Recipe::new(
+        Container::CraftingTable,
+        h.state.profile(),
+        vec![Step::Left(Grid::Table(0, 0)), Step::Right(Grid::Craft)],
+    );
+    recipe.steps[1].delay = ms(100);
+
+    std::fs::create_dir(h.recipe("delayed"))?;
+    recipe.to_writer(File::create(h.recipe("delayed").join(io::FILENAME_CLICKS))?)?;
+    h.state.reload_recipes()?;
+
+    h.script.tap(ms(10), &[VirtualKey::G]);
+    h.run_until(ms(200))?;
+
+    let (x, y) = position(&Grid::Craft);
+    assert_eq!(
+        h.script.log()[3..],
+        [
+            // from the start of the first step
+            (ms(110), Output::Cursor(x, y)),
+            (ms(117), Output::MouseDown(MouseButton::Right)),
+            (ms(117), Output::MouseUp(MouseButton::Right)),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn scales_recipe_and_step_intervals_by_speed() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+    let mut recipe = Recipe::new(
+        Container::CraftingTable,
+        h.state.profile(),
+        vec![Step::Left(Grid::Table(0, 0)), Step::Left(Grid::Craft)],
+    );
+    recipe.interval = Some(ms(20));
+    recipe.steps[1].interval = Some(ms(10));
+
+    std::fs::create_dir(h.recipe("slow"))?;
+    recipe.to_writer(File::create(h.recipe("slow").join(io::FILENAME_CLICKS))?)?;
+    h.state.reload_recipes()?;
+
+    h.script.tap(ms(10), &[VirtualKey::LAlt, VirtualKey::J]);
+    h.script.tap(ms(50), &[VirtualKey::LAlt, VirtualKey::J]);
+    h.script.tap(ms(100), &[VirtualKey::G]);
+    h.run_until(ms(200))?;
+    assert_eq!(h.state.speed(), 2.0);
+
+    let times: Vec<_> = h.script.log().into_iter().map(|(at, _)| at).collect();
+    assert_eq!(
+        times,
+        [ms(101), ms(110), ms(110), ms(120), ms(125), ms(125)]
+    );
+
+    // down to the slowest and no further
+    for i in 0..8 {
+        h.script
+            .tap(ms(200 + 50 * i), &[VirtualKey::LAlt, VirtualKey::H]);
+    }
+    h.run_until(ms(650))?;
+    assert_eq!(h.state.speed(), 0.25);
+
+    Ok(())
+}
+
+#[test]
+fn abort_stops_playback() -> Result<()> {
+    let recipe: Vec<Grid> = (0..20).map(|x| Grid::Hotbar(x % 9)).collect();
+    let mut h = Harness::new(&[("long", recipe.as_slice())])?;
+
+    h.script.tap(ms(10), &[VirtualKey::G]);
+    h.script.tap(ms(60), &[VirtualKey::Grave]);
+    h.run_until(ms(59))?;
+    assert!(matches!(h.state.detail(), Detail::Playing { .. }));
+
+    h.run_until(ms(500))?;
+    assert!(matches!(h.state.detail(), Detail::Idle));
+
+    let clicks = h
+        .script
+        .outputs()
+        .iter()
+        .filter(|output| matches!(output, Output::MouseDown(_)))
+        .count();
+    assert!(0 < clicks && clicks < recipe.len());
+
+    Ok(())
+}
+
+#[test]
+fn trades_and_returns_cursor() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+    let origin = (300, 600);
+
+    h.script.move_to(ms(0), origin);
+    h.script.tap(ms(10), &[VirtualKey::R]);
+    h.run_until(ms(100))?;
+
+    assert!(matches!(
+        h.state.detail(),
+        Detail::TradingFirst {
+            state: TradeFirst::Waiting,
+            ..
+        }
+    ));
+
+    h.script.tap(ms(100), &[VirtualKey::LShift]);
+    h.run_until(ms(200))?;
+
+    assert!(matches!(h.state.detail(), Detail::Idle));
+    assert_eq!(
+        h.script.outputs(),
+        [
+            Output::MouseDown(MouseButton::Left),
+            Output::MouseUp(MouseButton::Left),
+            Output::Cursor(828, 474),
+            Output::MouseDown(MouseButton::Left),
+            Output::MouseUp(MouseButton::Left),
+            Output::Cursor(1080, 474),
+            Output::MouseDown(MouseButton::Left),
+            Output::MouseUp(MouseButton::Left),
+            Output::Cursor(828, 474),
+            Output::MouseDown(MouseButton::Left),
+            Output::MouseUp(MouseButton::Left),
+            Output::Cursor(origin.0, origin.1),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn deletes_selected_recipe_upon_confirmation() -> Result<()> {
+    let mut h = Harness::new(&[("a", &[Grid::Craft]), ("b", &[Grid::Craft])])?;
+
+    h.script.tap(ms(10), &[VirtualKey::LAlt, VirtualKey::D]);
+    h.run_until(ms(50))?;
+    assert!(matches!(h.state.detail(), Detail::Deleting));
+    assert!(h.recipe("b").exists());
+
+    h.script.tap(ms(50), &[VirtualKey::Return]);
+    h.run_until(ms(100))?;
+
+    assert!(matches!(h.state.detail(), Detail::Idle));
+    assert!(h.recipe("a").exists());
+    assert!(!h.recipe("b").exists());
+    assert_eq!(h.state.recipes.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn renames_selected_recipe() -> Result<()> {
+    let mut h = Harness::new(&[("a", &[Grid::Craft]), ("b", &[Grid::Craft])])?;
+
+    // select "a" by wrapping around
+    h.script.tap(ms(10), &[VirtualKey::XButton1]);
+    h.script.tap(ms(50), &[VirtualKey::LAlt, VirtualKey::A]);
+    h.run_until(ms(100))?;
+    assert!(matches!(h.state.detail(), Detail::Renaming { .. }));
+
+    h.state.push_text("c");
+    h.script.tap(ms(100), &[VirtualKey::Return]);
+    h.run_until(ms(150))?;
+
+    assert!(matches!(h.state.detail(), Detail::Idle));
+    assert!(!h.recipe("a").exists());
+    assert!(h.recipe("b").exists());
+    assert!(h.recipe("c").exists());
+
+    Ok(())
+}
+
+#[test]
+fn lock_ignores_toggles() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+
+    h.script
+        .tap(ms(10), &[VirtualKey::LControl, VirtualKey::MButton]);
+    h.script.tap(ms(50), &[VirtualKey::Z]);
+    h.run_until(ms(100))?;
+
+    assert!(h.state.is_locked());
+    assert!(!h.state.spam_left.is_active());
+    assert!(h.script.outputs().is_empty());
+
+    h.script
+        .tap(ms(100), &[VirtualKey::LControl, VirtualKey::MButton]);
+    h.script.tap(ms(150), &[VirtualKey::Z]);
+    h.run_until(ms(200))?;
+
+    assert!(!h.state.is_locked());
+    assert!(h.state.spam_left.is_active());
+
+    let outputs = h.script.outputs();
+    assert!(4 <= outputs.len());
+    for (i, output) in outputs.iter().enumerate() {
+        let expected = if i % 2 == 0 {
+            Output::MouseDown(MouseButton::Left)
+        } else {
+            Output::MouseUp(MouseButton::Left)
+        };
+
+        assert_eq!(output, &expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn arc_follows_right_click_unless_cancelled() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+
+    h.script.tap(ms(10), &[VirtualKey::Tab]);
+    h.script.tap(ms(50), &[VirtualKey::RButton]);
+    h.run_until(ms(99))?;
+
+    assert!(h.state.double_click_active());
+    assert!(h.script.outputs().is_empty());
+
+    h.run_until(ms(150))?;
+    assert_eq!(
+        h.script.log(),
+        [
+            (ms(100), Output::MouseDown(MouseButton::Right)),
+            (ms(100), Output::MouseUp(MouseButton::Right)),
+        ]
+    );
+
+    h.script.clear_log();
+    h.script.down(ms(150), VirtualKey::LControl);
+    h.script.tap(ms(160), &[VirtualKey::RButton]);
+    h.run_until(ms(180))?;
+    assert!(h.state.double_click_temporarily_disabled());
+
+    h.script.up(ms(180), VirtualKey::LControl);
+    h.run_until(ms(300))?;
+    assert!(h.state.double_click_active());
+    assert!(h.script.outputs().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn rebinds_and_writes_back_to_config() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+
+    h.script.tap(ms(10), &[VirtualKey::LAlt, VirtualKey::K]);
+    h.script.tap(ms(50), &[VirtualKey::XButton1]);
+    h.script.tap(ms(100), &[VirtualKey::Return]);
+    h.script.tap(ms(150), &[VirtualKey::LShift, VirtualKey::F]);
+    h.run_until(ms(160))?;
+
+    match h.state.detail() {
+        Detail::Rebinding {
+            selected,
+            capture: Some(capture),
+            ..
+        } => {
+            assert_eq!(*selected, 1);
+            assert_eq!(capture.held(), [VirtualKey::LShift, VirtualKey::F]);
+        }
+        _ => panic!("not capturing"),
+    }
+
+    // locked while capturing
+    h.script.tap(ms(200), &[VirtualKey::X]);
+    h.run_until(ms(250))?;
+    assert!(!h.state.spam_right.is_active());
+
+    h.script.tap(ms(250), &[VirtualKey::LAlt, VirtualKey::K]);
+    h.run_until(ms(300))?;
+    assert!(matches!(h.state.detail(), Detail::Idle));
+
+    let expected = Chord::new([VirtualKey::LShift, VirtualKey::F]);
+    assert_eq!(h.state.bindings().right, expected);
+    assert_eq!(Config::from_file(h.config_path())?.keys.right, expected);
+
+    h.script.tap(ms(300), &[VirtualKey::LShift, VirtualKey::F]);
+    h.run_until(ms(350))?;
+    assert!(h.state.spam_right.is_active());
+
+    Ok(())
+}
+
+#[test]
+fn captures_chords_with_abort_and_lock() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+
+    h.script.tap(ms(10), &[VirtualKey::LAlt, VirtualKey::K]);
+    h.script.tap(ms(50), &[VirtualKey::Return]);
+    h.script
+        .tap(ms(100), &[VirtualKey::LShift, VirtualKey::Grave]);
+    h.run_until(ms(150))?;
+
+    assert!(matches!(
+        h.state.detail(),
+        Detail::Rebinding {
+            capture: None,
+            conflict: None,
+            ..
+        }
+    ));
+    assert_eq!(
+        h.state.bindings().left,
+        Chord::new([VirtualKey::Grave, VirtualKey::LShift])
+    );
+
+    h.script.tap(ms(150), &[VirtualKey::Return]);
+    h.script.tap(
+        ms(200),
+        &[VirtualKey::LControl, VirtualKey::MButton, VirtualKey::X],
+    );
+    h.run_until(ms(250))?;
+
+    assert!(!h.state.is_locked());
+    assert_eq!(
+        h.state.bindings().left,
+        Chord::new([VirtualKey::MButton, VirtualKey::LControl, VirtualKey::X])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn conflicting_chord_is_rejected() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+
+    h.script.tap(ms(10), &[VirtualKey::LAlt, VirtualKey::K]);
+    h.script.tap(ms(50), &[VirtualKey::Return]);
+    h.script.tap(ms(100), &[VirtualKey::X]);
+    h.run_until(ms(150))?;
+
+    assert!(matches!(
+        h.state.detail(),
+        Detail::Rebinding {
+            selected: 0,
+            capture: None,
+            conflict: Some(chord),
+        } if chord == &Chord::new([VirtualKey::X])
+    ));
+    assert_eq!(h.state.bindings().left, Chord::new([VirtualKey::Z]));
+    assert!(!h.config_path().exists());
+
+    Ok(())
+}
+
+#[test]
+fn follows_windowed_game() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+    let window = ClientRect {
+        x: 2020,
+        y: 130,
+        width: 854,
+        height: 480,
+    };
+    let geometry = Geometry::new(
+        &Layout::new((854, 480), 3).offset(Coord(2020, 130)),
+        Container::CraftingTable,
+    );
+
+    h.script.set_window("Minecraft 1.8.9", window);
+    h.script.tap(ms(10), &[VirtualKey::B]);
+    h.script
+        .click_at(ms(50), Grid::Table(2, 1).coord(&geometry).unwrap().into());
+    h.script.click_at(ms(100), position(&Grid::Table(0, 0)));
+    h.script.tap(ms(150), &[VirtualKey::B]);
+    h.run_until(ms(200))?;
+
+    assert!(matches!(
+        h.state.detail(),
+        Detail::Naming { steps, .. } if steps == &[Step::Left(Grid::Table(2, 1))]
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn calibrates_and_validates_every_slot() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+    let origin = Coord(300, 200);
+    let expected = Geometry::new(
+        &Layout::new((1280, 720), 2).offset(origin),
+        Container::CraftingTable,
+    );
+    let centre = |grid: &Grid| grid.coord(&expected).unwrap().into();
+
+    h.script.set_window(
+        "Minecraft 1.8.9",
+        ClientRect {
+            x: origin.0,
+            y: origin.1,
+            width: 1280,
+            height: 720,
+        },
+    );
+    h.script.tap(ms(10), &[VirtualKey::LAlt, VirtualKey::L]);
+    for (i, grid) in [
+        Grid::Table(0, 0),
+        Grid::Table(2, 2),
+        Grid::Craft,
+        Grid::Inv(0, 0),
+        Grid::Inv(8, 2),
+        Grid::Hotbar(0),
+        Grid::Hotbar(8),
+    ]
+    .iter()
+    .enumerate()
+    {
+        h.script.click_at(ms(50 + 50 * i as u64), centre(grid));
+    }
+    h.run_until(ms(390))?;
+    assert!(matches!(h.state.detail(), Detail::Validating { .. }));
+
+    let calibration = Config::from_file(h.config_path())?.calibration.unwrap();
+    assert_eq!(calibration.resolution, (1280, 720));
+    assert_eq!(calibration.geometry, expected.offset(Coord(-300, -200)));
+
+    h.run_until(ms(6000))?;
+    assert!(matches!(h.state.detail(), Detail::Idle));
+
+    let cursors: Vec<_> = expected
+        .slots()
+        .map(|grid| {
+            let (x, y) = centre(&grid);
+            Output::Cursor(x, y)
+        })
+        .collect();
+    assert_eq!(h.script.outputs(), cursors);
+
+    Ok(())
+}
+
+// the items of every slot are brown but those of `empty`
+fn screen(empty: &[Grid]) -> image::RgbImage {
+    let mut image = image::RgbImage::from_pixel(1920, 1080, image::Rgb([150, 100, 50]));
+
+    for grid in empty {
+        let (x, y) = position(grid);
+        for dy in -24..24 {
+            for dx in -24..24 {
+                image.put_pixel(
+                    (x + dx) as u32,
+                    (y + dy) as u32,
+                    image::Rgb([139, 139, 139]),
+                );
+            }
+        }
+    }
+
+    image
+}
+
+#[test]
+fn repeats_playback_the_given_number_of_times() -> Result<()> {
+    let recipe = [Grid::Table(1, 1), Grid::Craft];
+    let mut h = Harness::new(&[("sticks", &recipe)])?;
+
+    h.script.tap(ms(10), &[VirtualKey::LAlt, VirtualKey::N]);
+    h.run_until(ms(40))?;
+    assert!(h.state.is_locked());
+
+    h.state.push_text("x");
+    h.script.tap(ms(50), &[VirtualKey::Return]);
+    h.run_until(ms(90))?;
+    assert_eq!(
+        h.script.outputs(),
+        [Output::MessageBox(
+            "Reason: `x` is not a number of times".to_string()
+        )]
+    );
+    h.script.clear_log();
+
+    h.state.push_text("3");
+    h.script.tap(ms(100), &[VirtualKey::Return]);
+    h.run_until(ms(140))?;
+    assert!(matches!(
+        h.state.detail(),
+        Detail::Playing { stages, progress, .. }
+            if stages[0].repeat == Repeat::Times(3) && progress.run == 2
+    ));
+
+    h.run_until(ms(300))?;
+    assert!(matches!(h.state.detail(), Detail::Idle));
+
+    // each run starts an interval after the last click of the previous one
+    let moves: Vec<_> = h
+        .script
+        .log()
+        .into_iter()
+        .filter(|(_, output)| matches!(output, Output::Cursor(..)))
+        .map(|(at, _)| at)
+        .collect();
+    assert_eq!(
+        moves,
+        [ms(101), ms(114), ms(128), ms(142), ms(156), ms(170)]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn loops_until_the_inputs_run_out() -> Result<()> {
+    let recipe = [Grid::Inv(0, 0), Grid::Table(0, 0)];
+    let mut h = Harness::new(&[("planks", &recipe)])?;
+    h.state.playback.stop_when_empty = true;
+    h.script.set_screen(screen(&[]));
+
+    // an empty count repeats until aborted
+    h.script.tap(ms(10), &[VirtualKey::LAlt, VirtualKey::N]);
+    h.script.tap(ms(50), &[VirtualKey::Return]);
+    h.run_until(ms(300))?;
+    assert!(matches!(
+        h.state.detail(),
+        Detail::Playing { stages, .. } if stages[0].repeat == Repeat::UntilAbort
+    ));
+
+    h.script.set_screen(screen(&[Grid::Inv(0, 0)]));
+    h.run_until(ms(400))?;
+    assert!(matches!(h.state.detail(), Detail::Idle));
+
+    let clicks = h
+        .script
+        .outputs()
+        .iter()
+        .filter(|output| matches!(output, Output::MouseDown(_)))
+        .count();
+    assert_eq!(clicks % 2, 0);
+    assert!(4 < clicks);
+
+    // off the slots before they are looked at, once after each run
+    let moves_off = h
+        .script
+        .outputs()
+        .iter()
+        .filter(|output| matches!(output, Output::Cursor(0, 0)))
+        .count();
+    assert_eq!(moves_off, clicks / 2);
+
+    // nor does it start without them
+    h.script.clear_log();
+    h.script.tap(ms(410), &[VirtualKey::G]);
+    h.run_until(ms(500))?;
+    assert_eq!(
+        h.script.outputs(),
+        [Output::MessageBox("Reason: Inv(0, 0) is empty".to_string())]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn plays_playlists_as_one_playback() -> Result<()> {
+    let planks = [Grid::Inv(0, 0), Grid::Table(0, 0)];
+    let sticks = [Grid::Table(1, 1), Grid::Craft];
+    let mut h = Harness::new(&[("planks", &planks), ("sticks", &sticks)])?;
+    std::fs::write(
+        h.recipes.path().join("tools.playlist.json"),
+        r#"{"stages":[{"recipe":"planks","repeat":2},{"recipe":"sticks"}]}"#,
+    )?;
+    h.state.reload_recipes()?;
+    assert_eq!(h.state.recipes.len(), 2);
+
+    h.script.tap(ms(10), &[VirtualKey::LAlt, VirtualKey::P]);
+    h.script.tap(ms(50), &[VirtualKey::Return]);
+    h.run_until(ms(90))?;
+    assert!(matches!(
+        h.state.detail(),
+        Detail::Playing {
+            stage: 0,
+            progress,
+            ..
+        } if progress.run == 2
+    ));
+
+    h.run_until(ms(110))?;
+    assert!(matches!(
+        h.state.detail(),
+        Detail::Playing { stages, stage: 1, progress, .. }
+            if stages[1].name == "sticks" && progress.run == 1
+    ));
+
+    h.run_until(ms(200))?;
+    assert!(matches!(h.state.detail(), Detail::Idle));
+
+    let moves: Vec<_> = h
+        .script
+        .outputs()
+        .into_iter()
+        .filter(|output| matches!(output, Output::Cursor(..)))
+        .collect();
+    let expected: Vec<_> = [planks.as_slice(), &planks, &sticks]
+        .concat()
+        .iter()
+        .map(|grid| {
+            let (x, y) = position(grid);
+            Output::Cursor(x, y)
+        })
+        .collect();
+    assert_eq!(moves, expected);
+
+    // a single abort stops every stage
+    h.script.clear_log();
+    h.script.tap(ms(210), &[VirtualKey::LAlt, VirtualKey::P]);
+    h.script.tap(ms(250), &[VirtualKey::Return]);
+    h.script.tap(ms(270), &[VirtualKey::Grave]);
+    h.run_until(ms(400))?;
+    assert!(matches!(h.state.detail(), Detail::Idle));
+    assert_eq!(
+        h.script
+            .outputs()
+            .iter()
+            .filter(|output| matches!(output, Output::MouseDown(_)))
+            .count(),
+        1
+    );
+
+    Ok(())
+}
+
+#[test]
+fn records_and_replays_timing() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+
+    h.script.tap(ms(10), &[VirtualKey::LAlt, VirtualKey::T]);
+    // before the first step, which is played right away
+    h.script.move_to(ms(40), (100, 100));
+    h.script.click_at(ms(100), position(&Grid::Table(0, 0)));
+    h.script.move_to(ms(150), (500, 300));
+    h.script.move_to(ms(180), (600, 350));
+    h.script.click_at(ms(250), position(&Grid::Craft));
+    h.script.tap(ms(300), &[VirtualKey::LAlt, VirtualKey::T]);
+    h.run_until(ms(330))?;
+    assert!(matches!(h.state.detail(), Detail::Naming { .. }));
+
+    h.take_screenshots()?;
+    h.state.push_text("timed");
+    h.script.tap(ms(350), &[VirtualKey::Return]);
+    h.run_until(ms(380))?;
+
+    let recipe = io::load_recipe(h.recipe("timed").join(io::FILENAME_CLICKS))?;
+    assert_eq!(recipe.interval, None);
+    assert_eq!(recipe.steps[0].delay, Duration::ZERO);
+    assert_eq!(recipe.steps[1].delay, ms(150));
+    assert_eq!(
+        recipe.steps[1].path.as_ref(),
+        [
+            Waypoint {
+                at: ms(50),
+                to: Coord(500, 300)
+            },
+            Waypoint {
+                at: ms(80),
+                to: Coord(600, 350)
+            },
+        ]
+    );
+
+    h.script.clear_log();
+    h.script.tap(ms(400), &[VirtualKey::G]);
+    h.run_until(ms(600))?;
+
+    let (x0, y0) = position(&Grid::Table(0, 0));
+    let (x1, y1) = position(&Grid::Craft);
+    assert_eq!(
+        h.script.log(),
+        [
+            (ms(401), Output::Cursor(x0, y0)),
+            (ms(407), Output::MouseDown(MouseButton::Left)),
+            (ms(407), Output::MouseUp(MouseButton::Left)),
+            // as recorded after the start of the first step, whose inputs are `intervals.play` apart
+            (ms(450), Output::Cursor(500, 300)),
+            (ms(480), Output::Cursor(600, 350)),
+            (ms(550), Output::Cursor(x1, y1)),
+            (ms(557), Output::MouseDown(MouseButton::Left)),
+            (ms(557), Output::MouseUp(MouseButton::Left)),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn undoes_the_last_step_while_recording() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+
+    h.script.tap(ms(10), &[VirtualKey::B]);
+    h.script.click_at(ms(50), position(&Grid::Table(0, 0)));
+    h.script.click_at(ms(100), position(&Grid::Table(0, 0)));
+    h.script.tap(ms(150), &[VirtualKey::LAlt, VirtualKey::U]);
+    h.script.click_at(ms(200), position(&Grid::Inv(8, 2)));
+    h.script.tap(ms(250), &[VirtualKey::LAlt, VirtualKey::U]);
+    h.run_until(ms(240))?;
+
+    match h.state.detail() {
+        Detail::Recording { steps, count, .. } => {
+            assert_eq!(
+                steps,
+                &[Step::Left(Grid::Table(0, 0)), Step::Left(Grid::Inv(8, 2))]
+            );
+            assert_eq!(*count, 1);
+        }
+        _ => panic!("not recording"),
+    }
+
+    h.run_until(ms(300))?;
+    assert!(matches!(
+        h.state.detail(),
+        Detail::Recording { steps, count: 1, .. } if steps == &[Step::Left(Grid::Table(0, 0))]
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn edits_and_saves_recipes() -> Result<()> {
+    let recipe = [Grid::Table(0, 0), Grid::Table(1, 1), Grid::Craft];
+    let mut h = Harness::new(&[("sticks", &recipe)])?;
+    let alt = |key| [VirtualKey::LAlt, key];
+
+    h.script.tap(ms(10), &alt(VirtualKey::E));
+    h.script.tap(ms(50), &[VirtualKey::XButton1]);
+    h.script.tap(ms(100), &alt(VirtualKey::V));
+    // retargets the copy
+    h.script.click_at(ms(150), position(&Grid::Inv(0, 0)));
+    h.script.tap(ms(200), &alt(VirtualKey::I));
+    h.script.tap(ms(250), &[VirtualKey::XButton2]);
+    h.script.tap(ms(300), &alt(VirtualKey::D));
+    h.script.tap(ms(350), &alt(VirtualKey::O));
+    h.run_until(ms(390))?;
+
+    assert!(matches!(
+        h.state.detail(),
+        Detail::Editing {
+            selected: 1,
+            edited: true,
+            ..
+        }
+    ));
+    // nothing is written until confirmed
+    assert_eq!(steps(&h.recipe("sticks"))?.len(), 3);
+
+    h.script.tap(ms(400), &[VirtualKey::Return]);
+    h.script.tap(ms(450), &alt(VirtualKey::E));
+    h.run_until(ms(500))?;
+
+    assert!(matches!(h.state.detail(), Detail::Idle));
+    assert_eq!(
+        steps(&h.recipe("sticks"))?.as_ref(),
+        [
+            Step::Left(Grid::Table(1, 1)),
+            Step::Left(Grid::Inv(0, 0)),
+            Step::Left(Grid::Craft),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn previews_steps_without_input() -> Result<()> {
+    let recipe = [Grid::Table(0, 0), Grid::Table(1, 1), Grid::Craft];
+    let mut h = Harness::new(&[("sticks", &recipe)])?;
+    let current = |h: &Harness| match h.state.detail() {
+        Detail::Previewing { current, .. } => Some(*current),
+        _ => None,
+    };
+
+    h.script.tap(ms(10), &[VirtualKey::LAlt, VirtualKey::W]);
+    h.run_until(ms(20))?;
+    assert_eq!(current(&h), Some(0));
+
+    h.run_until(ms(520))?;
+    assert_eq!(current(&h), Some(1));
+
+    h.run_until(ms(1020))?;
+    assert_eq!(current(&h), Some(2));
+
+    h.run_until(ms(1520))?;
+    assert!(matches!(h.state.detail(), Detail::Idle));
+    assert!(h.script.outputs().is_empty());
+
+    // closes early
+    h.script.tap(ms(1600), &[VirtualKey::LAlt, VirtualKey::W]);
+    h.script.tap(ms(1700), &[VirtualKey::LAlt, VirtualKey::W]);
+    h.run_until(ms(1650))?;
+    assert_eq!(current(&h), Some(0));
+    h.run_until(ms(1750))?;
+    assert!(matches!(h.state.detail(), Detail::Idle));
+
+    Ok(())
+}
+
+#[test]
+fn dry_runs_log_instead_of_playing() -> Result<()> {
+    let recipe = [Grid::Table(0, 0), Grid::Craft];
+    let mut h = Harness::new(&[("sticks", &recipe)])?;
+
+    h.script.tap(ms(10), &[VirtualKey::LAlt, VirtualKey::Y]);
+    h.run_until(ms(500))?;
+
+    assert!(matches!(h.state.detail(), Detail::Idle));
+    assert!(h.script.outputs().is_empty());
+
+    let log = std::fs::read_to_string(h.recipe(io::FILENAME_DRY_RUN))?;
+    let lines: Vec<_> = log.lines().collect();
+    let (x, y) = position(&Grid::Table(0, 0));
+
+    assert_eq!(lines.len(), 4);
+    assert_eq!(
+        lines[0],
+        format!("0ms Table(0, 0) Move(Table(0, 0)) at ({x}, {y})")
+    );
+    let (x, y) = position(&Grid::Craft);
+    assert!(lines[3].ends_with(&format!(" Craft Click(Left) at ({x}, {y})")));
+
+    Ok(())
+}
+
+#[test]
+fn dry_runs_keep_the_latest_lines_and_write_out_all_when_aborted() -> Result<()> {
+    let recipe = [Grid::Table(0, 0); 40];
+    let mut h = Harness::new(&[("sticks", &recipe)])?;
+
+    h.script.tap(ms(10), &[VirtualKey::LAlt, VirtualKey::Y]);
+    h.run_until(ms(199))?;
+
+    let Detail::Playing {
+        dry_run: Some(dry_run),
+        ..
+    } = h.state.detail()
+    else {
+        panic!("not dry running");
+    };
+    assert_eq!(dry_run.lines.len(), DryRun::LINES);
+    let last = dry_run.lines.back().cloned();
+
+    h.script.tap(ms(200), &[VirtualKey::Grave]);
+    h.run_until(ms(250))?;
+    assert!(matches!(h.state.detail(), Detail::Idle));
+
+    let log = std::fs::read_to_string(h.recipe(io::FILENAME_DRY_RUN))?;
+    assert!(DryRun::LINES < log.lines().count());
+    assert_eq!(log.lines().last().map(str::to_string), last);
+
+    Ok(())
+}
+
+#[test]
+fn grabs_the_pictures_from_the_screen() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+    let frames = TempDir::new()?;
+    let (before, after) = (
+        frames.path().join("before.png"),
+        frames.path().join("after.png"),
+    );
+    RgbImage::from_pixel(1920, 1080, image::Rgb([200, 0, 0])).save(&before)?;
+    RgbImage::from_pixel(1920, 1080, image::Rgb([0, 200, 0])).save(&after)?;
+
+    h.script.load_screen(&before)?;
+    h.script.tap(ms(10), &[VirtualKey::B]);
+    h.script.click_at(ms(50), position(&Grid::Table(0, 0)));
+    h.run_until(ms(100))?;
+
+    h.script.load_screen(&after)?;
+    h.script.tap(ms(100), &[VirtualKey::B]);
+    h.run_until(ms(150))?;
+
+    // no screenshots are needed
+    h.state.push_text("planks");
+    h.script.tap(ms(150), &[VirtualKey::Return]);
+    h.run_until(ms(200))?;
+
+    assert!(matches!(h.state.detail(), Detail::Idle));
+
+    let thumbnail = image::open(h.recipe("planks").join(io::FILENAME_THUMBNAIL))?.into_rgb8();
+    let item = image::open(h.recipe("planks").join(io::FILENAME_ITEM))?.into_rgb8();
+
+    assert_eq!(thumbnail.dimensions(), (io::INV_WIDTH, io::INV_HEIGHT));
+    assert_eq!(thumbnail.get_pixel(0, 0), &image::Rgb([200, 0, 0]));
+    assert_eq!(item.dimensions(), (io::ITEM_WIDTH, io::ITEM_HEIGHT));
+    assert_eq!(item.get_pixel(0, 0), &image::Rgb([0, 200, 0]));
+
+    Ok(())
+}
+
+#[test]
+fn reports_screens_which_cannot_be_grabbed() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+
+    // smaller than the inventory is on it
+    h.script.set_screen(RgbImage::new(640, 360));
+    h.script.tap(ms(10), &[VirtualKey::B]);
+    h.run_until(ms(50))?;
+
+    assert!(matches!(h.state.detail(), Detail::Idle));
+    assert!(matches!(
+        h.script.outputs().as_slice(),
+        [Output::MessageBox(msg)] if msg.contains("is not on the screen")
+    ));
+
+    h.script.clear_log();
+    h.script.set_screen(RgbImage::new(1920, 1080));
+    h.script.tap(ms(50), &[VirtualKey::B]);
+    h.run_until(ms(100))?;
+    h.script.set_screen(RgbImage::new(640, 360));
+    h.script.tap(ms(100), &[VirtualKey::B]);
+    h.run_until(ms(150))?;
+
+    assert!(matches!(h.state.detail(), Detail::Recording { .. }));
+    assert!(matches!(
+        h.script.outputs().as_slice(),
+        [Output::MessageBox(msg)] if msg.contains("is not on the screen")
+    ));
+
+    h.script.set_screen(RgbImage::new(1920, 1080));
+    h.script.tap(ms(150), &[VirtualKey::B]);
+    h.run_until(ms(200))?;
+
+    assert!(matches!(
+        h.state.detail(),
+        Detail::Naming { frames, .. } if frames.item.is_some() && frames.after.is_some()
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn binds_the_screenshots_taken_while_recording() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+    let shoot = |h: &Harness, name: &str, color: [u8; 3]| {
+        RgbImage::from_pixel(1920, 1080, image::Rgb(color)).save(h.screenshots.path().join(name))
+    };
+
+    // before recording
+    shoot(&h, "stray.png", [255, 255, 255])?;
+    h.script.tap(ms(10), &[VirtualKey::B]);
+    h.script.click_at(ms(50), position(&Grid::Table(0, 0)));
+    h.run_until(ms(60))?;
+
+    shoot(&h, "inventory.png", [200, 0, 0])?;
+    h.script.tap(ms(100), &[VirtualKey::B]);
+    h.run_until(ms(150))?;
+
+    assert!(matches!(
+        h.state.detail(),
+        Detail::Naming { frames: Frames { screenshots: Some(screenshots), .. }, .. }
+            if screenshots == &[h.screenshots.path().join("inventory.png")]
+    ));
+
+    h.state.push_text("planks");
+    h.script.tap(ms(150), &[VirtualKey::Return]);
+    h.run_until(ms(200))?;
+
+    assert!(matches!(h.state.detail(), Detail::Naming { .. }));
+    assert!(matches!(
+        h.script.outputs().as_slice(),
+        [Output::MessageBox(msg)] if msg.contains("but 1 was taken while recording")
+    ));
+
+    shoot(&h, "item.png", [0, 200, 0])?;
+    h.script.tap(ms(200), &[VirtualKey::Return]);
+    h.run_until(ms(250))?;
+
+    assert!(matches!(h.state.detail(), Detail::Idle));
+
+    let thumbnail = image::open(h.recipe("planks").join(io::FILENAME_THUMBNAIL))?.into_rgb8();
+    let item = image::open(h.recipe("planks").join(io::FILENAME_ITEM))?.into_rgb8();
+
+    assert_eq!(thumbnail.get_pixel(0, 0), &image::Rgb([200, 0, 0]));
+    assert_eq!(item.get_pixel(0, 0), &image::Rgb([0, 200, 0]));
+
+    Ok(())
+}
+
+#[test]
+fn refuses_to_play_on_another_inventory_unless_confirmed() -> Result<()> {
+    let recipe = [Grid::Inv(0, 1), Grid::Table(0, 0), Grid::Inv(8, 2)];
+    let mut h = Harness::new(&[("sticks", &recipe)])?;
+    let fixture = |name: &str| -> Result<RgbImage> {
+        let path = format!("{}/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+        Ok(image::open(path)?.into_rgb8())
+    };
+    let screen = |inventory: &RgbImage| {
+        let geometry = Geometry::new(&Layout::new((1920, 1080), 3), Container::CraftingTable);
+        let (Coord(x, y), _) = geometry.player().unwrap();
+        let mut screen = RgbImage::new(1920, 1080);
+        image::imageops::replace(&mut screen, inventory, x.into(), y.into());
+        screen
+    };
+
+    fixture("thumbnail.png")?.save(h.recipe("sticks").join(io::FILENAME_THUMBNAIL))?;
+    h.script.set_screen(screen(&fixture("rearranged.png")?));
+    h.script.tap(ms(10), &[VirtualKey::G]);
+    h.run_until(ms(50))?;
+
+    assert!(matches!(
+        h.state.detail(),
+        Detail::Mismatched { slots, .. } if slots.as_ref() == [Grid::Inv(0, 1)]
+    ));
+    assert!(h.script.outputs().is_empty());
+
+    h.script.tap(ms(50), &[VirtualKey::Return]);
+    h.run_until(ms(60))?;
+    assert!(matches!(h.state.detail(), Detail::Playing { .. }));
+
+    // as recorded
+    h.script.tap(ms(100), &[VirtualKey::Grave]);
+    h.run_until(ms(150))?;
+    h.script.set_screen(screen(&fixture("thumbnail.png")?));
+    h.script.tap(ms(150), &[VirtualKey::G]);
+    h.run_until(ms(160))?;
+    assert!(matches!(h.state.detail(), Detail::Playing { .. }));
+
+    Ok(())
+}
+
+#[test]
+fn verifies_each_stage_and_what_it_left() -> Result<()> {
+    let planks = [Grid::Inv(0, 1), Grid::Table(0, 0)];
+    let sticks = [Grid::Inv(4, 1), Grid::Table(1, 1)];
+    let mut h = Harness::new(&[("planks", &planks), ("sticks", &sticks)])?;
+    let fixture = |name: &str| -> Result<RgbImage> {
+        let path = format!("{}/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+        Ok(image::open(path)?.into_rgb8())
+    };
+    let geometry = Geometry::new(&Layout::new((1920, 1080), 3), Container::CraftingTable);
+    let (Coord(x, y), _) = geometry.player().unwrap();
+    let mut screen = RgbImage::new(1920, 1080);
+    image::imageops::replace(&mut screen, &fixture("thumbnail.png")?, x.into(), y.into());
+
+    fixture("thumbnail.png")?.save(h.recipe("planks").join(io::FILENAME_THUMBNAIL))?;
+    fixture("rearranged.png")?.save(h.recipe("sticks").join(io::FILENAME_THUMBNAIL))?;
+    fixture("rearranged.png")?.save(h.recipe("sticks").join(io::FILENAME_AFTER))?;
+    std::fs::write(
+        h.recipes.path().join("tools.playlist.json"),
+        r#"{"stages":[{"recipe":"planks"},{"recipe":"sticks"}]}"#,
+    )?;
+    h.state.reload_recipes()?;
+    h.script.set_screen(screen);
+
+    // the first stage matches, the second one is looked at once it is due
+    h.script.tap(ms(10), &[VirtualKey::LAlt, VirtualKey::P]);
+    h.script.tap(ms(50), &[VirtualKey::Return]);
+    h.run_until(ms(150))?;
+    assert!(matches!(
+        h.state.detail(),
+        Detail::Mismatched { stages, slots, .. }
+            if stages.len() == 1 && slots.as_ref() == [Grid::Inv(4, 1)]
+    ));
+    assert_eq!(h.state.recipes.get_path()?, Some(&h.recipe("sticks")));
+    assert!(
+        h.script
+            .outputs()
+            .contains(&Output::MouseDown(MouseButton::Left))
+    );
+
+    // played anyway, but it did not leave what it did when it was recorded
+    h.script.clear_log();
+    h.script.tap(ms(150), &[VirtualKey::Return]);
+    h.run_until(ms(300))?;
+    assert!(matches!(h.state.detail(), Detail::Idle));
+    assert!(matches!(
+        h.script.outputs().last(),
+        Some(Output::MessageBox(msg)) if msg.contains("sticks left Inv(4, 1) unlike when it was recorded")
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn learns_the_item_under_the_cursor() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+    let geometry = Geometry::new(&Layout::new((1920, 1080), 3), Container::CraftingTable);
+    let (Coord(x, y), _) = geometry.player().unwrap();
+    let path = format!("{}/fixtures/thumbnail.png", env!("CARGO_MANIFEST_DIR"));
+    let thumbnail = image::open(path)?.into_rgb8();
+    // as empty slots look
+    let mut screen = RgbImage::from_pixel(1920, 1080, image::Rgb([139, 139, 139]));
+    image::imageops::replace(&mut screen, &thumbnail, x.into(), y.into());
+    h.script.set_screen(screen);
+
+    // nothing to learn from an empty slot
+    h.script.move_to(ms(10), position(&Grid::Table(0, 0)));
+    h.script.tap(ms(20), &[VirtualKey::LAlt, VirtualKey::M]);
+    h.run_until(ms(50))?;
+    assert!(!matches!(h.state.detail(), Detail::Learning { .. }));
+
+    h.script.move_to(ms(50), position(&Grid::Inv(0, 1)));
+    h.script.tap(ms(60), &[VirtualKey::LAlt, VirtualKey::M]);
+    h.run_until(ms(100))?;
+    assert!(matches!(h.state.detail(), Detail::Learning { .. }));
+
+    h.state.push_text("iron_ingot");
+    h.script.tap(ms(100), &[VirtualKey::Return]);
+    h.run_until(ms(150))?;
+
+    assert!(matches!(h.state.detail(), Detail::Idle));
+    assert!(
+        h.items
+            .path()
+            .join(Library::SLOTS)
+            .join("iron_ingot.png")
+            .exists()
+    );
+    let slot = crate::item::crop(&thumbnail, Grid::Inv(0, 1), Container::CraftingTable).unwrap();
+    assert_eq!(
+        h.state
+            .library()
+            .classify(&slot)
+            .and_then(|stack| stack.item),
+        Some("iron_ingot".to_string())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn fetches_items_wherever_they_are() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+    let geometry = Geometry::new(&Layout::new((1920, 1080), 3), Container::CraftingTable);
+    let (Coord(x, y), _) = geometry.player().unwrap();
+    let screen = |name: &str| -> Result<RgbImage> {
+        let path = format!("{}/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+        let mut screen = RgbImage::from_pixel(1920, 1080, image::Rgb([139, 139, 139]));
+        image::imageops::replace(
+            &mut screen,
+            &image::open(path)?.into_rgb8(),
+            x.into(),
+            y.into(),
+        );
+        Ok(screen)
+    };
+
+    h.script.set_screen(screen("thumbnail.png")?);
+    h.script.move_to(ms(10), position(&Grid::Inv(4, 1)));
+    h.script.tap(ms(20), &[VirtualKey::LAlt, VirtualKey::M]);
+    h.run_until(ms(50))?;
+    h.state.push_text("redstone");
+    h.script.tap(ms(50), &[VirtualKey::Return]);
+    h.run_until(ms(100))?;
+
+    for (name, item) in [("dust", "redstone"), ("gems", "diamond")] {
+        let recipe = Recipe::new(
+            Container::CraftingTable,
+            h.state.profile(),
+            vec![
+                Step::Fetch(item.to_string(), Grid::Table(0, 0)),
+                Step::Fetch(item.to_string(), Grid::Table(1, 0)),
+            ],
+        );
+        std::fs::create_dir(h.recipe(name))?;
+        recipe.to_writer(File::create(h.recipe(name).join(io::FILENAME_CLICKS))?)?;
+    }
+    h.state.reload_recipes()?;
+
+    // moved since it was learned
+    h.script.set_screen(screen("rearranged.png")?);
+    h.script.clear_log();
+    h.script.tap(ms(100), &[VirtualKey::G]);
+    h.run_until(ms(150))?;
+
+    assert!(matches!(h.state.detail(), Detail::Idle));
+    assert_eq!(
+        h.script.outputs(),
+        [Output::MessageBox(
+            "Reason: no `diamond` in the inventory or the hotbar".to_string()
+        )]
+    );
+
+    h.script.clear_log();
+    h.script.tap(ms(150), &[VirtualKey::XButton2]);
+    h.script.tap(ms(200), &[VirtualKey::G]);
+    h.run_until(ms(300))?;
+
+    // the first stack of redstone is where the iron was, and the second one is taken next even
+    // though the first one still shows on the screen
+    let outputs: Vec<_> = [
+        Grid::Inv(0, 1),
+        Grid::Table(0, 0),
+        Grid::Inv(5, 1),
+        Grid::Table(1, 0),
+    ]
+    .iter()
+    .flat_map(|grid| {
+        let (x, y) = position(grid);
+        [
+            Output::Cursor(x, y),
+            Output::MouseDown(MouseButton::Left),
+            Output::MouseUp(MouseButton::Left),
+        ]
+    })
+    .collect();
+    assert_eq!(h.script.outputs(), outputs);
+
+    Ok(())
+}
+
+#[test]
+fn refuses_to_fetch_where_the_screen_cannot_be_read() -> Result<()> {
+    let mut h = Harness::new(&[])?;
+    let recipe = Recipe::new(
+        Container::CraftingTable,
+        h.state.profile(),
+        vec![Step::Fetch("redstone".to_string(), Grid::Table(0, 0))],
+    );
+    std::fs::create_dir(h.recipe("dust"))?;
+    recipe.to_writer(File::create(h.recipe("dust").join(io::FILENAME_CLICKS))?)?;
+    h.state.reload_recipes()?;
+
+    h.script.tap(ms(10), &[VirtualKey::G]);
+    h.run_until(ms(50))?;
+
+    assert!(matches!(h.state.detail(), Detail::Idle));
+    assert_eq!(
+        h.script.outputs(),
+        [Output::MessageBox(
+            "Reason: the screen cannot be read to find `redstone`".to_string()
+        )]
+    );
+
+    Ok(())
+}
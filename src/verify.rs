@@ -0,0 +1,179 @@
+use crate::{
+    coord::Coord,
+    grid::{Container, Grid, Picture},
+};
+use image::{RgbImage, imageops};
+
+// of the 64 bits of the hashes, beyond which two slots or icons hold different items
+pub(crate) const THRESHOLD: u32 = 12;
+// of the mean colour, since the hash is blind to e.g. planks or wool of another colour
+pub(crate) const TOLERANCE: i32 = 24;
+
+// the slots which hold something else on `inventory` than on `thumbnail`, both cropped to the
+// inventory with the hotbar at any scale. slots which are not on them are skipped.
+pub fn mismatches(
+    thumbnail: &RgbImage,
+    inventory: &RgbImage,
+    container: Container,
+    slots: &[Grid],
+) -> Vec<Grid> {
+    slots
+        .iter()
+        .filter(|grid| {
+            match (
+                inner(thumbnail, **grid, container),
+                inner(inventory, **grid, container),
+            ) {
+                (Some(expected), Some(actual)) => {
+                    let (expected_colour, actual_colour) = (colour(&expected), colour(&actual));
+
+                    THRESHOLD < (hash(&expected) ^ hash(&actual)).count_ones()
+                        || (0..3).any(|i| TOLERANCE < (expected_colour[i] - actual_colour[i]).abs())
+                }
+                _ => false,
+            }
+        })
+        .copied()
+        .collect()
+}
+
+// the middle of the slot, away from the border and the stack size
+fn inner(image: &RgbImage, grid: Grid, container: Container) -> Option<RgbImage> {
+    let (width, height) = image.dimensions();
+    let centre = grid.picture(
+        container,
+        Picture::Thumbnail,
+        Coord(width as i32, height as i32),
+    )?;
+    // a quarter of a slot, which is a ninth of the width
+    let quarter = (width / 36).max(1) as i32;
+    let Coord(x, y) = centre.sub(Coord(quarter, quarter));
+
+    Some(
+        imageops::crop_imm(
+            image,
+            x as u32,
+            y as u32,
+            2 * quarter as u32,
+            2 * quarter as u32,
+        )
+        .to_image(),
+    )
+}
+
+// whether each pixel of a 9x8 greyscale copy is brighter than the next one to the right, which
+// survives scaling and slight differences in colour
+pub fn hash(image: &RgbImage) -> u64 {
+    let grey = imageops::grayscale(image);
+    let small = imageops::resize(&grey, 9, 8, imageops::FilterType::Triangle);
+
+    (0..8)
+        .flat_map(|y| (0..8).map(move |x| (x, y)))
+        .fold(0, |hash, (x, y)| {
+            let is_brighter = small.get_pixel(x, y).0[0] > small.get_pixel(x + 1, y).0[0];
+            hash << 1 | u64::from(is_brighter)
+        })
+}
+
+// the mean of each channel
+pub fn colour(image: &RgbImage) -> [i32; 3] {
+    let total = u64::from(image.width() * image.height()).max(1);
+    let mut sums = [0; 3];
+
+    for pixel in image.pixels() {
+        for (sum, c) in sums.iter_mut().zip(pixel.0) {
+            *sum += u64::from(c);
+        }
+    }
+
+    sums.map(|sum| (sum / total) as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    fn fixture(name: &str) -> Result<RgbImage> {
+        let path = format!("{}/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+        Ok(image::open(path)?.into_rgb8())
+    }
+
+    #[test]
+    fn finds_the_slots_which_were_rearranged() -> Result<()> {
+        let thumbnail = fixture("thumbnail.png")?;
+        let rearranged = fixture("rearranged.png")?;
+        let slots = [
+            Grid::Inv(0, 1),
+            Grid::Inv(1, 1),
+            Grid::Inv(4, 1),
+            Grid::Inv(8, 2),
+            Grid::Hotbar(6),
+            // not on the thumbnail
+            Grid::Table(0, 0),
+        ];
+
+        assert_eq!(
+            mismatches(&thumbnail, &rearranged, Container::CraftingTable, &slots),
+            [Grid::Inv(0, 1), Grid::Inv(4, 1)]
+        );
+        assert!(mismatches(&thumbnail, &thumbnail, Container::CraftingTable, &slots).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn finds_the_slots_which_were_recoloured() -> Result<()> {
+        let thumbnail = fixture("thumbnail.png")?;
+        let mut recoloured = thumbnail.clone();
+        let container = Container::CraftingTable;
+        let (width, height) = thumbnail.dimensions();
+        let Coord(x, y) = Grid::Inv(0, 1)
+            .picture(
+                container,
+                Picture::Thumbnail,
+                Coord(width as i32, height as i32),
+            )
+            .expect("on the thumbnail");
+        let half = (width / 18) as i32;
+
+        // the same shapes in red, as if they were of another wool
+        for y in y - half..y + half {
+            for x in x - half..x + half {
+                let pixel = recoloured.get_pixel_mut(x as u32, y as u32);
+                pixel.0 = [pixel.0[0], pixel.0[1] / 2, pixel.0[2] / 2];
+            }
+        }
+
+        let slots = [Grid::Inv(0, 1), Grid::Inv(1, 1)];
+
+        let expected = inner(&thumbnail, Grid::Inv(0, 1), container).expect("on the thumbnail");
+        let actual = inner(&recoloured, Grid::Inv(0, 1), container).expect("on the thumbnail");
+
+        // alike to the hash alone
+        assert!((hash(&expected) ^ hash(&actual)).count_ones() <= THRESHOLD);
+        assert_eq!(
+            mismatches(&thumbnail, &recoloured, container, &slots),
+            [Grid::Inv(0, 1)]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn matches_at_another_scale() -> Result<()> {
+        let thumbnail = fixture("thumbnail.png")?;
+        let (width, height) = thumbnail.dimensions();
+        let smaller = imageops::resize(
+            &thumbnail,
+            width * 2 / 3,
+            height * 2 / 3,
+            imageops::FilterType::Nearest,
+        );
+        let slots: Vec<_> = (0..9).map(Grid::Hotbar).collect();
+
+        assert!(mismatches(&thumbnail, &smaller, Container::CraftingTable, &slots).is_empty());
+
+        Ok(())
+    }
+}